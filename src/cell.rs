@@ -0,0 +1,112 @@
+use std::path;
+
+use termion::color;
+
+use self::super::colors::ColorType;
+use self::super::formatter::{color_for, strlen, EntryConfig};
+use self::super::hyperlink::link;
+
+// A run of text in one style.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Segment {
+    pub text: String,
+    pub color: Option<ColorType>,
+    // Hyperlink target, used with --hyperlink.
+    pub link: Option<path::PathBuf>,
+    // Columns, when the text is not one per grapheme, like emoji icons.
+    pub width: Option<usize>,
+    // Padded up to the column width, inside its color.
+    pub fill: bool,
+}
+
+impl Segment {
+    pub fn new(text: String, color: Option<ColorType>) -> Segment {
+        Segment { text, color, link: None, width: None, fill: false }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width.unwrap_or_else(|| strlen(&self.text))
+    }
+
+    pub fn render(&self, config: &EntryConfig, pad: usize) -> String {
+        let text = match self.link {
+            Some(ref path) if config.hyperlink => link(path, &self.text),
+            _ => self.text.clone(),
+        };
+        // NOTE: Padding stays outside the link, so it is not clickable.
+        let text = text + &" ".repeat(pad);
+        match self.color {
+            Some(ref color) => format!("{}{}{}", color::Fg(color_for(config, color)), text, color::Fg(color::Reset)),
+            None => text,
+        }
+    }
+}
+
+// What a formatter makes of an entry. Measured and rendered from the same
+// segments, so predicted widths cannot drift from the output.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Cell {
+    pub segments: Vec<Segment>,
+}
+
+impl Cell {
+    pub fn new() -> Cell {
+        Cell::default()
+    }
+
+    pub fn push(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    pub fn width(&self) -> usize {
+        self.segments.iter().map(Segment::width).sum()
+    }
+
+    // Without styles or links.
+    pub fn text(&self) -> String {
+        self.segments.iter().map(|segment| segment.text.as_str()).collect()
+    }
+
+    // Padded to width: in the fill segment if there is one, else at the end.
+    pub fn render(&self, config: &EntryConfig, width: usize) -> String {
+        let pad = width.saturating_sub(self.width());
+        let fill = self.segments.iter().position(|segment| segment.fill);
+        let mut out: String = self.segments.iter().enumerate().map(|(i, segment)| {
+            segment.render(config, if fill == Some(i) { pad } else { 0 })
+        }).collect();
+        if fill.is_none() {
+            out.push_str(&" ".repeat(pad));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod cell_tests {
+    use super::*;
+    use self::super::super::testing::default_config;
+
+    fn cell(fill: bool) -> Cell {
+        let mut cell = Cell::new();
+        cell.push(Segment { width: Some(2), ..Segment::new("*".to_string(), None) });
+        cell.push(Segment { fill, ..Segment::new("main.rs".to_string(), Some(ColorType::RecognizedFile)) });
+        cell.push(Segment::new("/".to_string(), None));
+        cell
+    }
+
+    #[test]
+    fn for_width() {
+        assert_eq!(10, cell(false).width());
+        assert_eq!("*main.rs/", cell(false).text());
+    }
+
+    #[test]
+    fn for_padding() {
+        let config = default_config();
+        let color = color::Fg(color_for(&config, &ColorType::RecognizedFile));
+        let reset = color::Fg(color::Reset);
+        assert_eq!(format!("*{}main.rs{}/  ", color, reset), cell(false).render(&config, 12));
+        assert_eq!(format!("*{}main.rs  {}/", color, reset), cell(true).render(&config, 12));
+        assert_eq!(format!("*{}main.rs{}/", color, reset), cell(true).render(&config, 4));
+    }
+}
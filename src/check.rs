@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path;
+use std::str::FromStr;
+
+use serde_yaml;
+
+use self::super::colors::{ColorType, RealColor};
+use self::super::defaults::user_files;
+use self::super::IconSet;
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+enum Section {
+    Files,
+    Folders,
+    FileAliases,
+    FolderAliases,
+    Colors,
+}
+
+impl FromStr for Section {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Section, String> {
+        match value {
+            "files" => Ok(Section::Files),
+            "folders" => Ok(Section::Folders),
+            "file_aliases" => Ok(Section::FileAliases),
+            "folder_aliases" => Ok(Section::FolderAliases),
+            "colors" => Ok(Section::Colors),
+            _ => Err(format!("Unknown Section: {}", value)),
+        }
+    }
+}
+
+impl Section {
+    // The user config dir file holding it, like `files.yaml` or `files_ascii.yaml`.
+    fn of_file(name: &str) -> Section {
+        name.trim_end_matches(".yaml").trim_end_matches("_emoji").trim_end_matches("_ascii").parse().unwrap()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Problem {
+    pub path: path::PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+// First line from `from` on that defines `key`, 1-based.
+fn line_of(text: &str, key: &str, from: usize) -> Option<usize> {
+    let keys = [format!("{}:", key), format!("\"{}\":", key), format!("'{}':", key)];
+    text.lines().enumerate().skip(from).find(|&(_, line)| {
+        keys.iter().any(|k| line.trim_start().starts_with(&k[..]))
+    }).map(|(i, _)| i + 1)
+}
+
+type Sections = BTreeMap<Section, BTreeMap<String, String>>;
+
+// Config files merged in the order colorls loads them, remembering where
+// every key came from.
+#[derive(Default)]
+struct Checker {
+    files: usize,
+    problems: Vec<Problem>,
+    merged: Sections,
+    origins: HashMap<(Section, String), (path::PathBuf, Option<usize>)>,
+    replaced: HashMap<Section, path::PathBuf>,
+}
+
+impl Checker {
+    fn problem(&mut self, path: &path::Path, line: Option<usize>, message: String) {
+        self.problems.push(Problem { path: path.to_path_buf(), line, message });
+    }
+
+    // A user config dir file replaces its section, a `.colorls.yaml` adds to all of them.
+    fn add(&mut self, path: &path::Path, text: &str, section: Option<Section>) {
+        self.files += 1;
+        if text.trim().is_empty() {
+            return;
+        }
+        let sections: Vec<(Section, BTreeMap<String, String>, usize)> = match section {
+            Some(section) => match serde_yaml::from_str(text) {
+                Ok(map) => {
+                    self.merged.insert(section, BTreeMap::new());
+                    self.replaced.insert(section, path.to_path_buf());
+                    vec![(section, map, 0)]
+                }
+                Err(e) => return self.problem(path, None, e.to_string()),
+            },
+            None => match serde_yaml::from_str::<BTreeMap<String, BTreeMap<String, String>>>(text) {
+                Ok(sections) => {
+                    let mut found = vec![];
+                    for (name, map) in sections {
+                        let line = line_of(text, &name, 0);
+                        match name.parse() {
+                            Ok(section) => found.push((section, map, line.unwrap_or(0))),
+                            Err(e) => self.problem(path, line, e),
+                        }
+                    }
+                    found
+                }
+                Err(e) => return self.problem(path, None, e.to_string()),
+            },
+        };
+        for (section, map, from) in sections {
+            for (key, value) in map {
+                let line = line_of(text, &key, from);
+                if section == Section::Colors {
+                    if let Err(e) = key.parse::<ColorType>() {
+                        self.problem(path, line, e);
+                    }
+                    if let Err(e) = value.parse::<RealColor>() {
+                        self.problem(path, line, e);
+                    }
+                }
+                self.origins.insert((section, key.clone()), (path.to_path_buf(), line));
+                self.merged.entry(section).or_default().insert(key, value);
+            }
+        }
+    }
+
+    fn check_fallback(&mut self, section: Section, key: &str) {
+        if !self.merged.get(&section).map(|map| map.contains_key(key)).unwrap_or(false) {
+            let path = self.replaced.get(&section).cloned().unwrap_or_default();
+            self.problem(&path, None, format!("missing the `{}` fallback", key));
+        }
+    }
+
+    // NOTE: Aliases resolve a single hop, so one pointing at another alias is as good as missing.
+    fn check_aliases(&mut self, aliases: Section, targets: Section) {
+        let empty = BTreeMap::new();
+        let merged = self.merged.clone();
+        let (aliases_map, targets_map) = (merged.get(&aliases).unwrap_or(&empty), merged.get(&targets).unwrap_or(&empty));
+        for (alias, target) in aliases_map {
+            if targets_map.contains_key(target) {
+                continue;
+            }
+            let mut chain = vec![&alias[..]];
+            let mut next = &target[..];
+            while let Some(target) = aliases_map.get(next) {
+                if chain.contains(&next) {
+                    break;
+                }
+                chain.push(next);
+                next = target;
+            }
+            let message = if next == alias {
+                chain.push(next);
+                format!("alias {} is part of a cycle: {}", alias, chain.join(" -> "))
+            } else if chain.len() > 1 {
+                format!("alias {} points at alias {}, only one hop is resolved", alias, target)
+            } else {
+                format!("alias {} points at missing key {}", alias, target)
+            };
+            let (path, line) = self.origins[&(aliases, alias.clone())].clone();
+            self.problem(&path, line, message);
+        }
+    }
+}
+
+// Parses the embedded defaults for the icon set, then the user config dir and
+// `.colorls.yaml` files over them. Returns how many files were checked, and what is wrong.
+pub fn check_config(icon_set: IconSet, user_dir: Option<&path::Path>, local: &[path::PathBuf]) -> (usize, Vec<Problem>) {
+    let mut checker = Checker::default();
+    for (name, yaml) in user_files(icon_set) {
+        checker.add(&path::Path::new("(embedded)").join(name), yaml, Some(Section::of_file(name)));
+    }
+    let mut files = vec![];
+    if let Some(dir) = user_dir {
+        files.extend(user_files(icon_set).into_iter().map(|(name, _)| (dir.join(name), Some(Section::of_file(name)))));
+    }
+    files.extend(local.iter().map(|path| (path.clone(), None)));
+    for (path, section) in files {
+        match fs::read_to_string(&path) {
+            Ok(text) => checker.add(&path, &text, section),
+            Err(_) if section.is_some() && !path.exists() => {}
+            Err(e) => checker.problem(&path, None, e.to_string()),
+        }
+    }
+    checker.check_fallback(Section::Files, "file");
+    checker.check_fallback(Section::Folders, "folder");
+    checker.check_aliases(Section::FileAliases, Section::Files);
+    checker.check_aliases(Section::FolderAliases, Section::Folders);
+    (checker.files, checker.problems)
+}
+
+#[cfg(test)]
+mod check_tests {
+    use super::*;
+    use self::super::super::testing::TempDir;
+
+    #[test]
+    fn for_defaults() {
+        for icon_set in &[IconSet::Nerd, IconSet::Emoji, IconSet::Ascii] {
+            assert_eq!((user_files(*icon_set).len(), vec![]), check_config(*icon_set, None, &[]));
+        }
+    }
+
+    #[test]
+    fn for_bad_colors() {
+        let dir = TempDir::new("check-colors");
+        fs::write(dir.join("colors.yaml"), "dir: blue\nfile: red\nexec: purple\n").unwrap();
+        let (files, problems) = check_config(IconSet::Nerd, Some(&dir.0), &[]);
+        assert_eq!(user_files(IconSet::Nerd).len() + 1, files);
+        let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(vec![
+            format!("{}:3: Unknown RealColor: purple", dir.join("colors.yaml").display()),
+            format!("{}:2: Unknown ColorType: file", dir.join("colors.yaml").display()),
+        ], problems);
+    }
+
+    #[test]
+    fn for_aliases_and_fallbacks() {
+        let dir = TempDir::new("check-aliases");
+        fs::write(dir.join("folders.yaml"), "deploy: D\nconfig: C\n").unwrap();
+        let local = dir.join(".colorls.yaml");
+        fs::write(&local, "file_aliases:\n  a: b\n  b: a\n  hs: haskell\n  c: a\nfolder_aliases:\n  ops: deploy\nicons:\n  x: y\n").unwrap();
+        let (_, problems) = check_config(IconSet::Nerd, Some(&dir.0), std::slice::from_ref(&local));
+        let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        let local = local.display();
+        assert_eq!(vec![
+            format!("{}:8: Unknown Section: icons", local),
+            format!("{}: missing the `folder` fallback", dir.join("folders.yaml").display()),
+            format!("{}:2: alias a is part of a cycle: a -> b -> a", local),
+            format!("{}:3: alias b is part of a cycle: b -> a -> b", local),
+            format!("{}:5: alias c points at alias a, only one hop is resolved", local),
+            format!("{}:4: alias hs points at missing key haskell", local),
+        ], problems);
+    }
+}
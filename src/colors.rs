@@ -1,5 +1,7 @@
 use serde::de::{self, Visitor, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
 use std::fmt;
+use std::str::FromStr;
 use termion::color;
 
 #[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
@@ -21,19 +23,27 @@ pub enum ColorType {
     Tree,
     Empty,
     Normal,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    Setuid,
+    Setgid,
+    Sticky,
+    OtherWritable,
+    Hidden,
+    OtherUser,
+    Root,
+    Image,
+    Audio,
+    Video,
+    Archive,
 }
 
-struct ColorTypeVisitor;
-impl Visitor for ColorTypeVisitor {
-    type Value = ColorType;
-
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("one of unrecognized_file, recognized_file, dir, dead_link, link, write, read, exec, no_access, day_old, hour_old, no_modifier, report, user, tree, empty, normal")
-    }
+impl FromStr for ColorType {
+    type Err = String;
 
-    fn visit_str<E>(self, value: &str) -> Result<ColorType, E>
-        where E: de::Error
-    {
+    fn from_str(value: &str) -> Result<ColorType, String> {
         match value {
             "unrecognized_file" => Ok(ColorType::UnrecognizedFile),
             "recognized_file" => Ok(ColorType::RecognizedFile),
@@ -52,11 +62,41 @@ impl Visitor for ColorTypeVisitor {
             "tree" => Ok(ColorType::Tree),
             "empty" => Ok(ColorType::Empty),
             "normal" => Ok(ColorType::Normal),
-            _ => Err(E::custom(format!("Unknown ColorType: {}", value)))
+            "fifo" => Ok(ColorType::Fifo),
+            "socket" => Ok(ColorType::Socket),
+            "block_device" => Ok(ColorType::BlockDevice),
+            "char_device" => Ok(ColorType::CharDevice),
+            "setuid" => Ok(ColorType::Setuid),
+            "setgid" => Ok(ColorType::Setgid),
+            "sticky" => Ok(ColorType::Sticky),
+            "other_writable" => Ok(ColorType::OtherWritable),
+            "hidden" => Ok(ColorType::Hidden),
+            "other_user" => Ok(ColorType::OtherUser),
+            "root" => Ok(ColorType::Root),
+            "image" => Ok(ColorType::Image),
+            "audio" => Ok(ColorType::Audio),
+            "video" => Ok(ColorType::Video),
+            "archive" => Ok(ColorType::Archive),
+            _ => Err(format!("Unknown ColorType: {}", value)),
         }
     }
 }
 
+struct ColorTypeVisitor;
+impl Visitor for ColorTypeVisitor {
+    type Value = ColorType;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("one of unrecognized_file, recognized_file, dir, dead_link, link, write, read, exec, no_access, day_old, hour_old, no_modifier, report, user, tree, empty, normal, fifo, socket, block_device, char_device, setuid, setgid, sticky, other_writable, hidden, other_user, root, image, audio, video, archive")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<ColorType, E>
+        where E: de::Error
+    {
+        value.parse().map_err(E::custom)
+    }
+}
+
 impl Deserialize for ColorType {
     fn deserialize<D>(deserializer: D) -> Result<ColorType, D::Error>
         where D: Deserializer
@@ -65,6 +105,54 @@ impl Deserialize for ColorType {
     }
 }
 
+impl ColorType {
+    // As written in the color config.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ColorType::UnrecognizedFile => "unrecognized_file",
+            ColorType::RecognizedFile => "recognized_file",
+            ColorType::Dir => "dir",
+            ColorType::DeadLink => "dead_link",
+            ColorType::Link => "link",
+            ColorType::Write => "write",
+            ColorType::Read => "read",
+            ColorType::Exec => "exec",
+            ColorType::NoAccess => "no_access",
+            ColorType::DayOld => "day_old",
+            ColorType::HourOld => "hour_old",
+            ColorType::NoModifier => "no_modifier",
+            ColorType::Report => "report",
+            ColorType::User => "user",
+            ColorType::Tree => "tree",
+            ColorType::Empty => "empty",
+            ColorType::Normal => "normal",
+            ColorType::Fifo => "fifo",
+            ColorType::Socket => "socket",
+            ColorType::BlockDevice => "block_device",
+            ColorType::CharDevice => "char_device",
+            ColorType::Setuid => "setuid",
+            ColorType::Setgid => "setgid",
+            ColorType::Sticky => "sticky",
+            ColorType::OtherWritable => "other_writable",
+            ColorType::Hidden => "hidden",
+            ColorType::OtherUser => "other_user",
+            ColorType::Root => "root",
+            ColorType::Image => "image",
+            ColorType::Audio => "audio",
+            ColorType::Video => "video",
+            ColorType::Archive => "archive",
+        }
+    }
+}
+
+impl Serialize for ColorType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
 #[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum RealColor {
     Yellow,
@@ -78,17 +166,10 @@ pub enum RealColor {
     Black,
 }
 
-struct RealColorVisitor;
-impl Visitor for RealColorVisitor {
-    type Value = RealColor;
-
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("one of yellow, green, blue, red, cyan, magenta, grey, white, black")
-    }
+impl FromStr for RealColor {
+    type Err = String;
 
-    fn visit_str<E>(self, value: &str) -> Result<RealColor, E>
-        where E: de::Error
-    {
+    fn from_str(value: &str) -> Result<RealColor, String> {
         match value {
             "yellow" => Ok(RealColor::Yellow),
             "green" => Ok(RealColor::Green),
@@ -99,11 +180,26 @@ impl Visitor for RealColorVisitor {
             "grey" => Ok(RealColor::Grey),
             "white" => Ok(RealColor::White),
             "black" => Ok(RealColor::Black),
-            _ => Err(E::custom(format!("Unknown RealColor: {}", value)))
+            _ => Err(format!("Unknown RealColor: {}", value)),
         }
     }
 }
 
+struct RealColorVisitor;
+impl Visitor for RealColorVisitor {
+    type Value = RealColor;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("one of yellow, green, blue, red, cyan, magenta, grey, white, black")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<RealColor, E>
+        where E: de::Error
+    {
+        value.parse().map_err(E::custom)
+    }
+}
+
 impl Deserialize for RealColor {
     fn deserialize<D>(deserializer: D) -> Result<RealColor, D::Error>
         where D: Deserializer
@@ -112,7 +208,23 @@ impl Deserialize for RealColor {
     }
 }
 
-pub struct ColorWrapper(pub Box<color::Color>);
+impl RealColor {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            RealColor::Yellow => "yellow",
+            RealColor::Green => "green",
+            RealColor::Blue => "blue",
+            RealColor::Red => "red",
+            RealColor::Cyan => "cyan",
+            RealColor::Magenta => "magenta",
+            RealColor::Grey => "grey",
+            RealColor::White => "white",
+            RealColor::Black => "black",
+        }
+    }
+}
+
+pub struct ColorWrapper(pub Box<dyn color::Color>);
 
 impl color::Color for ColorWrapper {
     #[inline]
@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::io;
+use std::path;
+use std::str::FromStr;
+
+use serde_yaml;
+
+use self::super::colors::{ColorType, RealColor};
+use self::super::formatter::{EntryConfig, Options};
+use self::super::quoting::Quoting;
+use self::super::IconSet;
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Theme {
+    Dark,
+    Light,
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Theme, String> {
+        match value {
+            "dark" => Ok(Theme::Dark),
+            "light" => Ok(Theme::Light),
+            _ => Err(format!("Unknown Theme: {}", value)),
+        }
+    }
+}
+
+impl EntryConfig {
+    // The embedded icons, aliases and colors, 80 columns wide.
+    pub fn defaults(icon_set: IconSet, theme: Theme) -> EntryConfig {
+        let (files, folders) = match icon_set {
+            IconSet::Nerd => (include_str!("default_config/files.yaml"), include_str!("default_config/folders.yaml")),
+            IconSet::Emoji => (include_str!("default_config/files_emoji.yaml"), include_str!("default_config/folders_emoji.yaml")),
+            IconSet::Ascii => (include_str!("default_config/files_ascii.yaml"), include_str!("default_config/folders_ascii.yaml")),
+        };
+        let colors = match theme {
+            Theme::Dark => include_str!("default_config/dark_colors.yaml"),
+            Theme::Light => include_str!("default_config/light_colors.yaml"),
+        };
+        // NOTE: These are compiled in, so failing to parse them is a bug.
+        EntryConfig {
+            files: serde_yaml::from_str(files).unwrap(),
+            folders: serde_yaml::from_str(folders).unwrap(),
+            file_aliases: serde_yaml::from_str(include_str!("default_config/file_aliases.yaml")).unwrap(),
+            folder_aliases: serde_yaml::from_str(include_str!("default_config/folder_aliases.yaml")).unwrap(),
+            colors: serde_yaml::from_str(colors).unwrap(),
+            width: 80,
+            classify: false,
+            sniff: false,
+            mime: None,
+            mime_icons: serde_yaml::from_str(include_str!("default_config/mime.yaml")).unwrap(),
+            mime_colors: serde_yaml::from_str(include_str!("default_config/mime_colors.yaml")).unwrap(),
+            stat: true,
+            quoting: Quoting::default(),
+            hyperlink: false,
+            icon_width: icon_set.width(),
+        }
+    }
+}
+
+pub const LOCAL_CONFIG: &str = ".colorls.yaml";
+
+// Entries to add or replace in the defaults; every section is optional.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize)]
+pub struct Overrides {
+    #[serde(default)]
+    pub files: Options,
+    #[serde(default)]
+    pub folders: Options,
+    #[serde(default)]
+    pub file_aliases: Options,
+    #[serde(default)]
+    pub folder_aliases: Options,
+    #[serde(default)]
+    pub colors: HashMap<ColorType, RealColor>,
+}
+
+impl Overrides {
+    pub fn load(path: &path::Path) -> Result<Overrides, String> {
+        parse(path)
+    }
+}
+
+// `.colorls.yaml` files for a directory, from the repository root (or the
+// directory itself outside of one) down, so nearer files win.
+pub fn local_configs(directory: &path::Path) -> Vec<path::PathBuf> {
+    let directory = fs::canonicalize(directory).unwrap_or_else(|_| directory.to_path_buf());
+    let root = directory.ancestors().position(|dir| dir.join(".git").exists());
+    let dirs: Vec<_> = directory.ancestors().take(root.map(|i| i + 1).unwrap_or(1)).collect();
+    dirs.iter().rev().map(|dir| dir.join(LOCAL_CONFIG)).filter(|path| path.is_file()).collect()
+}
+
+impl EntryConfig {
+    pub fn merge(&mut self, overrides: Overrides) {
+        self.files.extend(overrides.files);
+        self.folders.extend(overrides.folders);
+        self.file_aliases.extend(overrides.file_aliases);
+        self.folder_aliases.extend(overrides.folder_aliases);
+        self.colors.extend(overrides.colors);
+    }
+}
+
+// What `config init` writes to the user config dir. Each file there
+// replaces its embedded counterpart, named after it for every icon set.
+pub const USER_FILES: [(&str, &str); 9] = [
+    ("files.yaml", include_str!("default_config/files.yaml")),
+    ("files_emoji.yaml", include_str!("default_config/files_emoji.yaml")),
+    ("files_ascii.yaml", include_str!("default_config/files_ascii.yaml")),
+    ("folders.yaml", include_str!("default_config/folders.yaml")),
+    ("folders_emoji.yaml", include_str!("default_config/folders_emoji.yaml")),
+    ("folders_ascii.yaml", include_str!("default_config/folders_ascii.yaml")),
+    ("file_aliases.yaml", include_str!("default_config/file_aliases.yaml")),
+    ("folder_aliases.yaml", include_str!("default_config/folder_aliases.yaml")),
+    ("colors.yaml", include_str!("default_config/dark_colors.yaml")),
+];
+
+// The USER_FILES an icon set reads, leaving out the icons of the others.
+pub fn user_files(icon_set: IconSet) -> Vec<(&'static str, &'static str)> {
+    let icons = match icon_set {
+        IconSet::Nerd => ["files.yaml", "folders.yaml"],
+        IconSet::Emoji => ["files_emoji.yaml", "folders_emoji.yaml"],
+        IconSet::Ascii => ["files_ascii.yaml", "folders_ascii.yaml"],
+    };
+    USER_FILES.iter().cloned().filter(|&(name, _)| {
+        icons.contains(&name) || !name.starts_with("files") && !name.starts_with("folders")
+    }).collect()
+}
+
+// $XDG_CONFIG_HOME/colorls, or ~/.config/colorls.
+pub fn user_config_dir() -> Option<path::PathBuf> {
+    env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()).map(path::PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| path::Path::new(&home).join(".config")))
+        .map(|dir| dir.join("colorls"))
+}
+
+// NOTE: An empty file is a YAML null, not an empty map.
+fn parse<T: Default + ::serde::Deserialize>(path: &path::Path) -> Result<T, String> {
+    let yaml = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if yaml.trim().is_empty() {
+        return Ok(T::default());
+    }
+    serde_yaml::from_str(&yaml).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// Files the user config dir has for the icon set, replacing their sections.
+// Nothing changes on error.
+pub fn load_user_config(config: &mut EntryConfig, dir: &path::Path, icon_set: IconSet) -> Result<Vec<path::PathBuf>, String> {
+    let mut loaded = config.clone();
+    let mut paths = vec![];
+    for (name, _) in user_files(icon_set) {
+        let path = dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        match name {
+            _ if name.starts_with("files") => loaded.files = parse(&path)?,
+            _ if name.starts_with("folders") => loaded.folders = parse(&path)?,
+            "file_aliases.yaml" => loaded.file_aliases = parse(&path)?,
+            "folder_aliases.yaml" => loaded.folder_aliases = parse(&path)?,
+            _ => loaded.colors = parse(&path)?,
+        }
+        paths.push(path);
+    }
+    // NOTE: Icon lookups index these, so listing without them would panic.
+    if !loaded.files.contains_key("file") || !loaded.folders.contains_key("folder") {
+        return Err(format!("{}: the files need a `file` and the folders a `folder` fallback", dir.display()));
+    }
+    *config = loaded;
+    Ok(paths)
+}
+
+// Writes the files missing from the user config dir, returning them.
+pub fn init_user_config(dir: &path::Path) -> io::Result<Vec<path::PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let mut written = vec![];
+    for &(name, yaml) in &USER_FILES {
+        let path = dir.join(name);
+        if !path.exists() {
+            fs::write(&path, yaml)?;
+            written.push(path);
+        }
+    }
+    Ok(written)
+}
+
+impl EntryConfig {
+    // The effective icons, aliases and colors, as a `.colorls.yaml` would have them.
+    pub fn dump(&self) -> String {
+        let sorted = |options: &Options| options.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<BTreeMap<_, _>>();
+        let mut dump = BTreeMap::new();
+        dump.insert("files", sorted(&self.files));
+        dump.insert("folders", sorted(&self.folders));
+        dump.insert("file_aliases", sorted(&self.file_aliases));
+        dump.insert("folder_aliases", sorted(&self.folder_aliases));
+        dump.insert("colors", self.colors.iter().map(|(k, v)| (k.name().to_string(), v.name().to_string())).collect());
+        serde_yaml::to_string(&dump).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod defaults_tests {
+    use super::*;
+    use self::super::super::testing::TempDir;
+
+    #[test]
+    fn for_every_set_and_theme() {
+        for icon_set in &[IconSet::Nerd, IconSet::Emoji, IconSet::Ascii] {
+            for theme in &[Theme::Dark, Theme::Light] {
+                let config = EntryConfig::defaults(*icon_set, *theme);
+                assert!(config.files.contains_key("file") && config.folders.contains_key("folder"));
+            }
+        }
+        assert_eq!(2, EntryConfig::defaults(IconSet::Emoji, Theme::Dark).icon_width);
+        assert_eq!(Ok(Theme::Light), "light".parse());
+    }
+
+    #[test]
+    fn for_local_configs() {
+        let dir = TempDir::new("local-config");
+        fs::create_dir_all(dir.join("repo/.git")).unwrap();
+        fs::create_dir_all(dir.join("repo/deploy/k8s")).unwrap();
+        fs::write(dir.join(LOCAL_CONFIG), "files:\n  proto: outside\n").unwrap();
+        fs::write(dir.join("repo").join(LOCAL_CONFIG), "files:\n  proto: P\ncolors:\n  dir: red\n").unwrap();
+        fs::write(dir.join("repo/deploy").join(LOCAL_CONFIG), "folders:\n  deploy: D\nfiles:\n  proto: Q\n").unwrap();
+        let configs = local_configs(&dir.join("repo/deploy/k8s"));
+        assert_eq!(vec![dir.join("repo").join(LOCAL_CONFIG), dir.join("repo/deploy").join(LOCAL_CONFIG)], configs);
+        let mut config = EntryConfig::defaults(IconSet::Nerd, Theme::Dark);
+        for path in &configs {
+            config.merge(Overrides::load(path).unwrap());
+        }
+        assert_eq!(("Q", "D", Some(&RealColor::Red)), (&config.files["proto"][..], &config.folders["deploy"][..], config.colors.get(&ColorType::Dir)));
+        assert!(config.files.contains_key("rs"));
+    }
+
+    #[test]
+    fn when_switching_icon_sets_after_init() {
+        let dir = TempDir::new("user-config-icons");
+        assert_eq!(USER_FILES.len(), init_user_config(&dir.0).unwrap().len());
+        fs::write(dir.join("files.yaml"), "file: N\n").unwrap();
+        fs::write(dir.join("files_ascii.yaml"), "file: A\n").unwrap();
+        let mut config = EntryConfig::defaults(IconSet::Ascii, Theme::Dark);
+        let paths = load_user_config(&mut config, &dir.0, IconSet::Ascii).unwrap();
+        assert!(paths.contains(&dir.join("files_ascii.yaml")) && !paths.contains(&dir.join("files.yaml")));
+        assert_eq!(("A", &EntryConfig::defaults(IconSet::Ascii, Theme::Dark).folders), (&config.files["file"][..], &config.folders));
+        let mut config = EntryConfig::defaults(IconSet::Emoji, Theme::Dark);
+        load_user_config(&mut config, &dir.0, IconSet::Emoji).unwrap();
+        assert_eq!(EntryConfig::defaults(IconSet::Emoji, Theme::Dark).files, config.files);
+    }
+
+    #[test]
+    fn when_outside_of_a_repository() {
+        let dir = TempDir::new("local-config-norepo");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join(LOCAL_CONFIG), "").unwrap();
+        fs::write(dir.join("sub").join(LOCAL_CONFIG), "colors:\n  dir: nope\n").unwrap();
+        assert_eq!(vec![dir.join("sub").join(LOCAL_CONFIG)], local_configs(&dir.join("sub")));
+        assert_eq!(Ok(Overrides::default()), Overrides::load(&dir.join(LOCAL_CONFIG)));
+        assert!(Overrides::load(&dir.join("sub").join(LOCAL_CONFIG)).is_err());
+    }
+}
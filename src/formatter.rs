@@ -1,14 +1,26 @@
-use std::cmp::Ordering;
+use std::cmp::{max, Ordering};
 use std::collections::HashMap;
 use std::ffi;
 use std::fmt;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
 use std::path;
+use std::sync::{Arc, OnceLock};
 
 use unicode_segmentation::UnicodeSegmentation;
 
 use termion::color;
 
+use self::super::cell::{Cell, Segment};
 use self::super::colors::{ColorType, ColorWrapper, RealColor};
+use self::super::mime::{family_keys, MimeDb};
+use self::super::quoting::Quoting;
+use self::super::size::{human_size, DiskUsage};
+use self::super::sniff::sniff;
+use self::super::table::{perms, Align, Column, TableCell};
+use self::super::timestamp::{TimeField, TimeFormat};
+use self::super::users::UserDb;
 
 pub type Options = HashMap<String, String>;
 
@@ -20,24 +32,156 @@ pub struct EntryConfig {
     pub folder_aliases: Options,
     pub colors: HashMap<ColorType, RealColor>,
     pub width: usize,
+    pub classify: bool,
+    pub sniff: bool,
+    pub mime: Option<Arc<MimeDb>>,
+    pub mime_icons: Options,
+    // Color classes for MIME types or families, like `image/*: image`.
+    pub mime_colors: HashMap<String, ColorType>,
+    // Without it, entries whose type came from read_dir are never stat'ed,
+    // giving up exec, permission bit, dead link and empty highlights.
+    pub stat: bool,
+    pub quoting: Quoting,
+    // Wrap names in OSC 8 file:// links.
+    pub hyperlink: bool,
+    // Terminal columns an icon takes; 0 leaves out icons and their space.
+    pub icon_width: usize,
+}
+
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+const S_ISVTX: u32 = 0o1000;
+const S_IWOTH: u32 = 0o0002;
+const EXEC_BITS: u32 = 0o0111;
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Kind {
+    File,
+    Dir,
+    Symlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    Setuid,
+    Setgid,
+    Sticky,
+    OtherWritable,
+}
+
+impl Kind {
+    pub fn is_dir(&self) -> bool {
+        matches!(*self, Kind::Dir | Kind::Sticky | Kind::OtherWritable)
+    }
+
+    fn of(meta: &fs::Metadata) -> Kind {
+        let mode = meta.mode();
+        match Kind::of_type(meta.file_type()) {
+            Kind::Dir if mode & S_ISVTX != 0 => Kind::Sticky,
+            Kind::Dir if mode & S_IWOTH != 0 => Kind::OtherWritable,
+            Kind::File if mode & S_ISUID != 0 => Kind::Setuid,
+            Kind::File if mode & S_ISGID != 0 => Kind::Setgid,
+            kind => kind,
+        }
+    }
+
+    // What read_dir already knows, without the permission bits.
+    fn of_type(file_type: fs::FileType) -> Kind {
+        if file_type.is_symlink() {
+            Kind::Symlink
+        } else if file_type.is_dir() {
+            Kind::Dir
+        } else if file_type.is_fifo() {
+            Kind::Fifo
+        } else if file_type.is_socket() {
+            Kind::Socket
+        } else if file_type.is_block_device() {
+            Kind::BlockDevice
+        } else if file_type.is_char_device() {
+            Kind::CharDevice
+        } else {
+            Kind::File
+        }
+    }
+
+    fn indicator(&self, meta: Option<&fs::Metadata>) -> &'static str {
+        match *self {
+            _ if self.is_dir() => "/",
+            Kind::Symlink => "@",
+            Kind::Fifo => "|",
+            Kind::Socket => "=",
+            Kind::File | Kind::Setuid | Kind::Setgid if is_exec(meta) => "*",
+            _ => "",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Kind::File => "file",
+            Kind::Dir => "dir",
+            Kind::Symlink => "symlink",
+            Kind::Fifo => "fifo",
+            Kind::Socket => "socket",
+            Kind::BlockDevice => "block_device",
+            Kind::CharDevice => "char_device",
+            Kind::Setuid => "setuid",
+            Kind::Setgid => "setgid",
+            Kind::Sticky => "sticky",
+            Kind::OtherWritable => "other_writable",
+        }
+    }
+
+    // shared-mime-info names for things that are not regular files.
+    fn inode_mime(&self) -> Option<&'static str> {
+        match *self {
+            Kind::Dir | Kind::Sticky | Kind::OtherWritable => Some("inode/directory"),
+            Kind::Symlink => Some("inode/symlink"),
+            Kind::Fifo => Some("inode/fifo"),
+            Kind::Socket => Some("inode/socket"),
+            Kind::BlockDevice => Some("inode/blockdevice"),
+            Kind::CharDevice => Some("inode/chardevice"),
+            Kind::File | Kind::Setuid | Kind::Setgid => None,
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Attr {
     icon: String,
+    // Rule the icon came from, like `rs`, `image` or `folder`.
+    key: String,
     color: ColorType,
+    kind: Kind,
+    indicator: &'static str,
+    mime: Option<String>,
+    recognized: bool,
 }
 
+impl Attr {
+    fn new(key: &str, icon: &str, color: ColorType, kind: Kind) -> Attr {
+        Attr {
+            icon: icon.to_string(),
+            key: key.to_string(),
+            color,
+            kind,
+            indicator: "",
+            mime: None,
+            recognized: false,
+        }
+    }
+}
+
+// Keys for special files, like `@symlink`, start with this so no extension
+// or folder name can pick them up.
+pub const SPECIAL: char = '@';
+
 fn get_file_attr(conf: &EntryConfig, suffix: &str) -> Attr {
-    match conf.files.get(suffix) {
+    match conf.files.get(suffix).filter(|_| !suffix.starts_with(SPECIAL)) {
         Some(icon) => Attr {
-            icon: icon.clone(),
-            color: ColorType::RecognizedFile,
-        },
-        None => Attr {
-            icon: conf.files.get("file").unwrap().clone(),
-            color: ColorType::UnrecognizedFile,
+            recognized: true,
+            ..Attr::new(suffix, icon, ColorType::RecognizedFile, Kind::File)
         },
+        None => Attr::new("file", &conf.files["file"], ColorType::UnrecognizedFile, Kind::File),
     }
 }
 
@@ -49,15 +193,13 @@ fn get_file_attr_alias(conf: &EntryConfig, suffix: &str) -> Attr {
 }
 
 fn get_folder_attr(conf: &EntryConfig, name: &str) -> Attr {
-    match conf.folders.get(name) {
-        Some(icon) => Attr {
-            icon: icon.clone(),
-            color: ColorType::Dir,
-        },
-        None => Attr {
-            icon: conf.folders.get("folder").unwrap().clone(),
-            color: ColorType::Dir,
+    match conf.folders.get(name).filter(|_| !name.starts_with(SPECIAL)) {
+        Some(icon) => Attr::new(name, icon, ColorType::Dir, Kind::Dir),
+        None if name.starts_with('.') => Attr {
+            kind: Kind::Dir,
+            ..get_special_attr(&conf.folders, "@hidden", "folder", ColorType::Hidden)
         },
+        None => Attr::new("folder", &conf.folders["folder"], ColorType::Dir, Kind::Dir),
     }
 }
 
@@ -69,42 +211,222 @@ fn get_folder_attr_alias(conf: &EntryConfig, name: &str) -> Attr {
 }
 
 fn filename_without_leading_dot(path: &path::Path) -> String {
-    let mut file_name = path.file_name().unwrap().to_str().unwrap().to_string();
+    let mut file_name = path.file_name().unwrap().to_string_lossy().into_owned();
     file_name.remove(0);
     file_name
 }
 
+// Icons for special files live next to the regular ones, under `@` keys.
+fn get_special_attr(icons: &Options, key: &str, fallback: &str, color: ColorType) -> Attr {
+    match icons.get(key) {
+        Some(icon) => Attr::new(key, icon, color, Kind::File),
+        None => Attr::new(fallback, &icons[fallback], color, Kind::File),
+    }
+}
+
+fn get_name_attr(config: &EntryConfig, path: &path::Path) -> Attr {
+    let filename_without_leading_dot = filename_without_leading_dot(path);
+    let default = ffi::OsStr::new(&filename_without_leading_dot);
+    let extension = path.extension().unwrap_or(default).to_string_lossy();
+    let attr = get_file_attr_alias(config, &extension);
+    let is_hidden = path.file_name().unwrap().as_bytes().starts_with(b".");
+    if is_hidden && attr.color == ColorType::UnrecognizedFile {
+        get_special_attr(&config.files, "@hidden", "file", ColorType::Hidden)
+    } else {
+        attr
+    }
+}
+
+fn lookup_mime(config: &EntryConfig, path: &path::Path) -> Option<String> {
+    let db = config.mime.as_ref()?;
+    let file_name = path.file_name()?.to_str()?;
+    db.lookup(file_name).map(|mime| mime.to_string())
+}
+
+// Unknown extensions can still be placed by their MIME type or family.
+fn get_mime_attr(config: &EntryConfig, path: &path::Path, attr: Attr) -> Attr {
+    let mime = match lookup_mime(config, path) {
+        Some(mime) => mime,
+        None => return attr,
+    };
+    let keys = family_keys(&mime);
+    let attr = match keys.iter().filter_map(|key| config.mime_icons.get(key)).next() {
+        Some(icon_key) if attr.color == ColorType::UnrecognizedFile => get_file_attr_alias(config, icon_key),
+        _ => attr,
+    };
+    // NOTE: Only plain file colors give way, and only to classes the theme has.
+    let color = keys.iter().filter_map(|key| config.mime_colors.get(key)).next()
+        .filter(|color| config.colors.contains_key(color));
+    match color {
+        Some(&color) if attr.color == ColorType::RecognizedFile || attr.color == ColorType::UnrecognizedFile => {
+            Attr { mime: Some(mime), color, ..attr }
+        }
+        _ => Attr { mime: Some(mime), ..attr },
+    }
+}
+
+fn is_exec(meta: Option<&fs::Metadata>) -> bool {
+    meta.map(|meta| meta.mode() & EXEC_BITS != 0).unwrap_or(false)
+}
+
+// Extensionless files get a second chance from their contents, with --sniff.
+fn get_sniffed_attr(config: &EntryConfig, path: &path::Path, meta: Option<&fs::Metadata>, attr: Attr) -> Attr {
+    if !config.sniff || path.extension().is_some() || attr.color != ColorType::UnrecognizedFile {
+        return attr;
+    }
+    match sniff(path) {
+        Some(ref sniffed) if sniffed.is_generic() && is_exec(meta) => attr,
+        Some(sniffed) => get_file_attr_alias(config, &sniffed.key()),
+        None => attr,
+    }
+}
+
+// Computed on first use, like `lstat`: directories cost an extra read_dir.
+fn is_empty(kind: Kind, path: &path::Path, meta: &OnceLock<Option<fs::Metadata>>, empty: &OnceLock<bool>) -> bool {
+    *empty.get_or_init(|| match kind {
+        Kind::File => lstat(path, meta).map(|meta| meta.len() == 0).unwrap_or(false),
+        Kind::Dir => {
+            trace("read_dir", path);
+            fs::read_dir(path).map(|mut dir| dir.next().is_none()).unwrap_or(false)
+        }
+        _ => false,
+    })
+}
+
+// Only worth checking every entry for when it shows.
+fn shows_empty(config: &EntryConfig) -> bool {
+    config.colors.contains_key(&ColorType::Empty) || config.folders.contains_key("@empty")
+}
+
+// Empty entries override the color, and plain folders get the empty folder icon.
+fn get_empty_attr(config: &EntryConfig, attr: Attr) -> Attr {
+    let icon = match config.folders.get("@empty") {
+        Some(empty) if attr.kind == Kind::Dir && attr.key == "folder" => empty.clone(),
+        _ => attr.icon,
+    };
+    Attr {
+        icon,
+        color: ColorType::Empty,
+        ..attr
+    }
+}
+
+// Executables keep their extension rule if they have one, like `run.sh`.
+fn get_exec_attr(config: &EntryConfig, meta: Option<&fs::Metadata>, attr: Attr) -> Attr {
+    if is_exec(meta) && attr.color == ColorType::UnrecognizedFile {
+        get_special_attr(&config.files, "@exec", "file", ColorType::Exec)
+    } else {
+        attr
+    }
+}
+
+#[cfg(test)]
 pub fn get_attr(config: &EntryConfig, path: &path::Path) -> Attr {
-    if path.is_dir() {
-        let file_name = path.file_name().unwrap().to_str().unwrap();
-        return get_folder_attr_alias(config, file_name);
+    attr_for(config, path, None, &OnceLock::new(), &OnceLock::new())
+}
+
+#[cfg(test)]
+fn trace(call: &'static str, path: &path::Path) {
+    self::super::testing::trace(call, path);
+}
+
+#[cfg(not(test))]
+fn trace(_call: &'static str, _path: &path::Path) {}
+
+fn lstat<'a>(path: &path::Path, meta: &'a OnceLock<Option<fs::Metadata>>) -> Option<&'a fs::Metadata> {
+    meta.get_or_init(|| {
+        trace("lstat", path);
+        fs::symlink_metadata(path).ok()
+    }).as_ref()
+}
+
+// `cache` and `empty` are filled in only if something actually needs them.
+fn attr_for(config: &EntryConfig, path: &path::Path, file_type: Option<fs::FileType>, cache: &OnceLock<Option<fs::Metadata>>, empty: &OnceLock<bool>) -> Attr {
+    let (kind, meta) = match file_type {
+        Some(file_type) if !config.stat => (Kind::of_type(file_type), None),
+        _ => match lstat(path, cache) {
+            Some(meta) => (Kind::of(meta), Some(meta)),
+            None => return get_name_attr(config, path),
+        },
+    };
+    let attr = match kind {
+        Kind::File => {
+            let attr = get_mime_attr(config, path, get_name_attr(config, path));
+            let attr = get_sniffed_attr(config, path, meta, attr);
+            get_exec_attr(config, meta, attr)
+        }
+        Kind::Dir => {
+            let file_name = path.file_name().unwrap().to_string_lossy();
+            get_folder_attr_alias(config, &file_name)
+        }
+        Kind::Symlink => {
+            let dead = meta.is_some() && {
+                trace("stat", path);
+                fs::metadata(path).is_err()
+            };
+            let color = if dead { ColorType::DeadLink } else { ColorType::Link };
+            get_special_attr(&config.files, "@symlink", "file", color)
+        }
+        Kind::Fifo => get_special_attr(&config.files, "@fifo", "file", ColorType::Fifo),
+        Kind::Socket => get_special_attr(&config.files, "@socket", "file", ColorType::Socket),
+        Kind::BlockDevice => get_special_attr(&config.files, "@block_device", "file", ColorType::BlockDevice),
+        Kind::CharDevice => get_special_attr(&config.files, "@char_device", "file", ColorType::CharDevice),
+        Kind::Setuid => get_special_attr(&config.files, "@setuid", "file", ColorType::Setuid),
+        Kind::Setgid => get_special_attr(&config.files, "@setgid", "file", ColorType::Setgid),
+        Kind::Sticky => get_special_attr(&config.folders, "@sticky", "folder", ColorType::Sticky),
+        Kind::OtherWritable => get_special_attr(&config.folders, "@other_writable", "folder", ColorType::OtherWritable),
+    };
+    let mime = match kind.inode_mime() {
+        Some(mime) if config.mime.is_some() => Some(mime.to_string()),
+        _ => attr.mime.clone().or_else(|| lookup_mime(config, path)),
+    };
+    let attr = Attr {
+        kind,
+        indicator: kind.indicator(meta),
+        mime,
+        ..attr
+    };
+    if meta.is_some() && shows_empty(config) && is_empty(kind, path, cache, empty) {
+        get_empty_attr(config, attr)
     } else {
-        let filename_without_leading_dot = filename_without_leading_dot(path);
-        let default = ffi::OsStr::new(&filename_without_leading_dot);
-        let extension = path.extension().unwrap_or(default).to_str().unwrap();
-        return get_file_attr_alias(config, extension);
-    }
-}
-
-fn color_for(config: &EntryConfig, color: &ColorType) -> ColorWrapper {
-    let boxed: Box<color::Color> = match config.colors.get(color).unwrap_or(&RealColor::Grey) {
-        &RealColor::Yellow => Box::new(color::Yellow),
-        &RealColor::Green => Box::new(color::Green),
-        &RealColor::Blue => Box::new(color::Blue),
-        &RealColor::Red => Box::new(color::Red),
-        &RealColor::Cyan => Box::new(color::Cyan),
-        &RealColor::Magenta => Box::new(color::Magenta),
-        &RealColor::Grey => Box::new(color::AnsiValue::rgb(2, 2, 2)),
-        &RealColor::White => Box::new(color::AnsiValue::rgb(0, 0, 0)),
-        &RealColor::Black => Box::new(color::AnsiValue::rgb(5, 5, 5)),
+        attr
+    }
+}
+
+// Name as shown in listings, with the -F suffix when asked for.
+pub fn decorated(config: &EntryConfig, entry: &Entry, name: String) -> String {
+    if config.classify {
+        name + entry.attr.indicator
+    } else {
+        name
+    }
+}
+
+pub fn color_for(config: &EntryConfig, color: &ColorType) -> ColorWrapper {
+    let boxed: Box<dyn color::Color> = match *config.colors.get(color).unwrap_or(&RealColor::Grey) {
+        RealColor::Yellow => Box::new(color::Yellow),
+        RealColor::Green => Box::new(color::Green),
+        RealColor::Blue => Box::new(color::Blue),
+        RealColor::Red => Box::new(color::Red),
+        RealColor::Cyan => Box::new(color::Cyan),
+        RealColor::Magenta => Box::new(color::Magenta),
+        RealColor::Grey => Box::new(color::AnsiValue::rgb(2, 2, 2)),
+        RealColor::White => Box::new(color::AnsiValue::rgb(0, 0, 0)),
+        RealColor::Black => Box::new(color::AnsiValue::rgb(5, 5, 5)),
     };
     ColorWrapper(boxed)
 }
 
-#[derive(Eq, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Entry {
     pub path: path::PathBuf,
+    // Shown for the entry: its file name, or a path for listings from stdin.
+    name: ffi::OsString,
     pub attr: Attr,
+    // From read_dir, which usually knows it without a syscall.
+    file_type: Option<fs::FileType>,
+    meta: OnceLock<Option<fs::Metadata>>,
+    empty: OnceLock<bool>,
 }
 
 impl Ord for Entry {
@@ -119,67 +441,252 @@ impl PartialOrd for Entry {
     }
 }
 
+impl Entry {
+    pub fn new(config: &EntryConfig, path: path::PathBuf, file_type: Option<fs::FileType>) -> Entry {
+        let (meta, empty) = (OnceLock::new(), OnceLock::new());
+        let attr = attr_for(config, &path, file_type, &meta, &empty);
+        let name = path.file_name().unwrap_or(path.as_os_str()).to_os_string();
+        Entry { path, name, attr, file_type, meta, empty }
+    }
+
+    pub fn named(self, name: ffi::OsString) -> Entry {
+        Entry { name, ..self }
+    }
+
+    pub fn name(&self) -> &ffi::OsStr {
+        &self.name
+    }
+
+    // lstat on first use, shared by every column that needs it.
+    pub fn metadata(&self) -> Option<&fs::Metadata> {
+        lstat(&self.path, &self.meta)
+    }
+
+    pub fn file_type(&self) -> Option<fs::FileType> {
+        self.file_type.or_else(|| self.metadata().map(|meta| meta.file_type()))
+    }
+
+    // Zero bytes, or no entries for a directory.
+    pub fn is_empty(&self) -> bool {
+        is_empty(self.kind(), &self.path, &self.meta, &self.empty)
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.attr.kind
+    }
+
+    pub fn key(&self) -> &str {
+        &self.attr.key
+    }
+
+    pub fn icon(&self) -> &str {
+        &self.attr.icon
+    }
+
+    pub fn color(&self) -> ColorType {
+        self.attr.color
+    }
+
+    pub fn indicator(&self) -> &'static str {
+        self.attr.indicator
+    }
+
+    pub fn mime(&self) -> Option<&str> {
+        self.attr.mime.as_ref().map(|mime| &mime[..])
+    }
+
+    pub fn is_recognized(&self) -> bool {
+        self.attr.recognized
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.path.file_name().map(|name| name.as_bytes().starts_with(b".")).unwrap_or(false)
+    }
+}
+
 impl PartialEq for Entry {
     fn eq(&self, other: &Entry) -> bool {
         self.path == other.path
     }
 }
 
+impl Eq for Entry {}
+
 pub trait Formatter: fmt::Debug {
-    fn format(&self, &EntryConfig, &Entry) -> String;
-    fn predict(&self, &Entry) -> usize;
+    fn cell(&self, config: &EntryConfig, entry: &Entry) -> Cell;
+
+    fn format(&self, config: &EntryConfig, entry: &Entry) -> String {
+        self.cell(config, entry).render(config, config.width)
+    }
+
+    // Columns an entry takes, with the gap before the next one.
+    fn predict(&self, config: &EntryConfig, entry: &Entry) -> usize {
+        self.cell(config, entry).width() + 1
+    }
+
+    // Formatters with columns get aligned as a table by TableTabulator.
+    fn row(&self, _config: &EntryConfig, _entry: &Entry) -> Option<Vec<TableCell>> {
+        None
+    }
 }
 
+// Fits most common types; longer ones push the name right.
+const MIME_WIDTH: usize = 24;
+const OWNER_WIDTH: usize = 8;
+// `1023.9 KiB` is the widest human_size gets.
+const SIZE_WIDTH: usize = 10;
+
 #[derive(Debug)]
-pub struct LongFormat;
+pub struct LongFormat {
+    pub time: TimeFormat,
+    pub columns: Vec<Column>,
+    pub users: UserDb,
+    pub numeric: bool,
+    // Set by --total-size: directories show everything below them.
+    pub sizes: Option<DiskUsage>,
+}
+
+impl LongFormat {
+    fn time(&self, entry: &Entry, field: Option<TimeField>) -> (String, ColorType) {
+        let format = TimeFormat { field: field.unwrap_or(self.time.field), ..self.time.clone() };
+        let time = entry.metadata().and_then(|meta| format.timestamp(meta));
+        match time {
+            Some(time) => (format.format(time), format.color(time)),
+            None => ("-".to_string(), ColorType::Normal),
+        }
+    }
+
+    fn user(&self, entry: &Entry) -> (String, ColorType) {
+        let uid = match entry.metadata() {
+            Some(meta) => meta.uid(),
+            None => return ("-".to_string(), ColorType::Normal),
+        };
+        let name = self.users.user_name(uid).filter(|_| !self.numeric);
+        (name.map(|n| n.to_string()).unwrap_or_else(|| uid.to_string()), self.users.user_color(uid))
+    }
+
+    fn group(&self, entry: &Entry) -> (String, ColorType) {
+        let gid = match entry.metadata() {
+            Some(meta) => meta.gid(),
+            None => return ("-".to_string(), ColorType::Normal),
+        };
+        let name = self.users.group_name(gid).filter(|_| !self.numeric);
+        (name.map(|n| n.to_string()).unwrap_or_else(|| gid.to_string()), self.users.group_color(gid))
+    }
+
+    fn size(&self, entry: &Entry) -> String {
+        match self.sizes {
+            Some(ref sizes) if entry.kind().is_dir() => human_size(sizes.total(&entry.path)),
+            Some(ref sizes) => entry.metadata().map(|meta| human_size(sizes.size_of(meta))).unwrap_or_else(|| "-".to_string()),
+            None => entry.metadata().map(|meta| human_size(meta.len())).unwrap_or_else(|| "-".to_string()),
+        }
+    }
+
+    fn column(&self, config: &EntryConfig, entry: &Entry, column: Column) -> TableCell {
+        let align = column.align();
+        match column {
+            Column::Perms => TableCell::new(entry.metadata().map(perms).unwrap_or_else(|| "-".to_string()), None, align),
+            Column::Size => TableCell::new(self.size(entry), None, align),
+            Column::User => {
+                let (user, color) = self.user(entry);
+                TableCell::new(user, Some(color), align)
+            }
+            Column::Group => {
+                let (group, color) = self.group(entry);
+                TableCell::new(group, Some(color), align)
+            }
+            Column::Time(field) => {
+                let (time, color) = self.time(entry, field);
+                TableCell::new(time, Some(color), align)
+            }
+            Column::Mime => TableCell::new(entry.mime().unwrap_or("-").to_string(), None, align),
+            Column::Icon => {
+                let icon = if config.icon_width == 0 { String::new() } else { entry.attr.icon.clone() };
+                TableCell { segment: Segment { width: Some(config.icon_width), ..Segment::new(icon, None) }, align }
+            }
+            Column::Name => {
+                let name = decorated(config, entry, config.quoting.quote(entry.path.as_os_str()));
+                TableCell { segment: Segment { link: Some(entry.path.clone()), ..Segment::new(name, Some(entry.attr.color)) }, align }
+            }
+        }
+    }
+
+    // Widths used outside of a table, where other rows are not known.
+    fn fixed_width(&self, column: Column) -> Option<usize> {
+        match column {
+            Column::User | Column::Group => Some(OWNER_WIDTH),
+            Column::Size => Some(SIZE_WIDTH),
+            Column::Mime => Some(MIME_WIDTH),
+            _ => None,
+        }
+    }
+
+    // Cells laid out on their own, skipping empty ones like disabled icons.
+    fn fixed_cells(&self, config: &EntryConfig, entry: &Entry) -> Vec<(TableCell, usize)> {
+        self.columns.iter().map(|column| {
+            let cell = self.column(config, entry, *column);
+            let width = self.fixed_width(*column).map(|width| max(width, cell.width())).unwrap_or_else(|| cell.width());
+            (cell, width)
+        }).filter(|(cell, _)| cell.segment.width != Some(0)).collect()
+    }
+}
+
 impl Formatter for LongFormat {
-    fn format(&self, config: &EntryConfig, entry: &Entry) -> String {
-        let name = entry.path.display();
-        let width = config.width - 2;
-        format!(
-            "{icon} {color}{name:<width$}{reset}",
-            name = name,
-            icon = entry.attr.icon,
-            color = color::Fg(color_for(config, &entry.attr.color)),
-            reset = color::Fg(color::Reset),
-            width = width,
-        )
+    fn cell(&self, config: &EntryConfig, entry: &Entry) -> Cell {
+        let cells = self.fixed_cells(config, entry);
+        let last = cells.len().saturating_sub(1);
+        let mut out = Cell::new();
+        for (i, (cell, width)) in cells.into_iter().enumerate() {
+            if i > 0 {
+                out.push(Segment::new(" ".to_string(), None));
+            }
+            let pad = " ".repeat(width - cell.width());
+            let TableCell { mut segment, align } = cell;
+            match align {
+                // NOTE: A trailing name takes up the rest of the column.
+                Align::Left if i == last && segment.link.is_some() => segment.fill = true,
+                Align::Left => segment.text.push_str(&pad),
+                Align::Right => out.push(Segment::new(pad, None)),
+            }
+            out.push(segment);
+        }
+        out
     }
 
-    fn predict(&self, entry: &Entry) -> usize {
-        strlen(&format!("{}", entry.path.display())) + 4
+    fn row(&self, config: &EntryConfig, entry: &Entry) -> Option<Vec<TableCell>> {
+        Some(self.columns.iter().map(|column| {
+            let mut cell = self.column(config, entry, *column);
+            // NOTE: Relative and iso times come padded; the table pads them itself.
+            if let Column::Time(_) = *column {
+                cell.segment.text = cell.segment.text.trim_end().to_string();
+            }
+            cell
+        }).collect())
     }
 }
 
 #[derive(Debug)]
 pub struct ShortFormat;
 
-fn short_name(l: &Entry) -> String {
-    l.path.file_name().unwrap().to_str().unwrap().to_string()
+fn short_name(config: &EntryConfig, l: &Entry) -> String {
+    config.quoting.quote(l.name())
 }
 
 impl Formatter for ShortFormat {
-    fn format(&self, config: &EntryConfig, entry: &Entry) -> String {
-        let name = short_name(entry);
-        let width = config.width - 2;
-        format!(
-            "{icon} {color}{name:<width$}{reset}",
-            name = name,
-            icon = entry.attr.icon,
-            color = color::Fg(color_for(config, &entry.attr.color)),
-            reset = color::Fg(color::Reset),
-            width = width,
-        )
-    }
-
-    fn predict(&self, entry: &Entry) -> usize {
-        strlen(&short_name(entry)) + 3
+    fn cell(&self, config: &EntryConfig, entry: &Entry) -> Cell {
+        let mut cell = Cell::new();
+        if config.icon_width > 0 {
+            cell.push(Segment { width: Some(config.icon_width + 1), ..Segment::new(format!("{} ", entry.attr.icon), None) });
+        }
+        let name = decorated(config, entry, short_name(config, entry));
+        cell.push(Segment { link: Some(entry.path.clone()), fill: true, ..Segment::new(name, Some(entry.attr.color)) });
+        cell
     }
 }
 
 // NOTE: Colors DO count to length. Sadly.
-fn strlen(s: &String) -> usize {
-    s.graphemes(true).count() as usize
+pub fn strlen(s: &str) -> usize {
+    s.graphemes(true).count()
 }
 
 #[cfg(test)]
@@ -187,17 +694,17 @@ mod strlen_tests {
     use super::*;
     #[test]
     fn for_normal_string() {
-        assert_eq!(6, strlen(&".local".to_string()))
+        assert_eq!(6, strlen(".local"))
     }
 
     #[test]
     fn for_string_with_icons() {
-        assert_eq!(7, strlen(&".local".to_string()))
+        assert_eq!(7, strlen(".local"))
     }
 
     #[test]
     fn for_string_with_weird_stuff() {
-        assert_eq!(7, strlen(&"a̐.local".to_string()))
+        assert_eq!(7, strlen("a̐.local"))
     }
 
     #[test]
@@ -217,3 +724,406 @@ mod strlen_tests {
         )
     }
 }
+
+#[cfg(test)]
+mod long_format_tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use libc;
+    use self::super::super::pool::Pool;
+    use self::super::super::size::SizeMode;
+    use self::super::super::testing::{default_config, TempDir};
+    use self::super::super::timestamp::{TimeField, TimeStyle, Zone};
+
+    fn config() -> EntryConfig {
+        let mut files = Options::new();
+        files.insert("file".to_string(), "F".to_string());
+        let mut colors = HashMap::new();
+        colors.insert(ColorType::DayOld, RealColor::Yellow);
+        colors.insert(ColorType::UnrecognizedFile, RealColor::Red);
+        colors.insert(ColorType::User, RealColor::Green);
+        EntryConfig {
+            files,
+            file_aliases: Options::new(),
+            folders: Options::new(),
+            folder_aliases: Options::new(),
+            colors,
+            width: 40,
+            classify: false,
+            sniff: false,
+            mime: None,
+            mime_icons: Options::new(),
+            mime_colors: HashMap::new(),
+            stat: true,
+            quoting: Quoting::default(),
+            hyperlink: false,
+            icon_width: 1,
+        }
+    }
+
+    fn current_ids() -> (u32, u32) {
+        unsafe { (libc::getuid(), libc::getgid()) }
+    }
+
+    fn long_format(mtime: SystemTime, users: UserDb, numeric: bool) -> LongFormat {
+        LongFormat {
+            time: TimeFormat {
+                style: TimeStyle::Relative,
+                field: TimeField::Modified,
+                zone: Zone::Utc,
+                now: mtime + Duration::from_secs(3 * 60 * 60),
+            },
+            columns: Column::defaults(false, false),
+            users,
+            numeric,
+            sizes: None,
+        }
+    }
+
+    #[test]
+    fn for_relative_time() {
+        let dir = TempDir::new("long-format");
+        let path = dir.join("file");
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let file = File::create(&path).unwrap();
+        file.set_len(1).unwrap();
+        file.set_modified(mtime).unwrap();
+        let config = config();
+        let entry = Entry::new(&config, path.clone(), None);
+        let (uid, gid) = current_ids();
+        let missing = path::Path::new("tests/fixtures/missing");
+        let format = long_format(mtime, UserDb::new(missing, missing, uid, gid), true);
+        let name = format!("{}", path.display());
+        assert_eq!(
+            format!(
+                "{green}{uid:<8}{reset} {green}{gid:<8}{reset} {}3 hours ago   {reset} F {}{:<w$}{reset}",
+                color::Fg(color::Yellow),
+                color::Fg(color::Red),
+                name,
+                green = color::Fg(color::Green),
+                reset = color::Fg(color::Reset),
+                uid = uid,
+                gid = gid,
+                w = 40 - 5 - 8 - 8 - 14,
+            ),
+            format.format(&config, &entry)
+        );
+        assert_eq!(8 + 1 + 8 + 1 + 14 + 1 + 1 + 1 + strlen(&name) + 1, format.predict(&config, &entry));
+    }
+
+    #[test]
+    fn for_owner_names() {
+        let dir = TempDir::new("long-format-owner");
+        let (uid, gid) = current_ids();
+        fs::write(dir.join("passwd"), format!("me:x:{}:{}::/:/bin/sh\n", uid, gid)).unwrap();
+        fs::write(dir.join("group"), format!("mine:x:{}:\n", gid)).unwrap();
+        let config = config();
+        let entry = Entry::new(&config, dir.join("passwd"), None);
+        let users = UserDb::new(&dir.join("passwd"), &dir.join("group"), uid, gid);
+        let named = long_format(SystemTime::now(), users, false).format(&config, &entry);
+        assert!(named.contains(&format!("{}me      {}", color::Fg(color::Green), color::Fg(color::Reset))));
+        assert!(named.contains(&format!("{}mine    {}", color::Fg(color::Green), color::Fg(color::Reset))));
+        let users = UserDb::new(&dir.join("passwd"), &dir.join("group"), uid, gid);
+        let numeric = long_format(SystemTime::now(), users, true).format(&config, &entry);
+        assert!(numeric.contains(&format!("{:<8}", uid)));
+        assert!(!numeric.contains("me "));
+    }
+
+    #[test]
+    fn for_total_sizes() {
+        let dir = TempDir::new("long-format-size");
+        fs::create_dir_all(dir.join("src/bin")).unwrap();
+        fs::write(dir.join("src/bin/main.rs"), vec![b' '; 2048]).unwrap();
+        let config = EntryConfig { width: 200, ..default_config() };
+        let (uid, gid) = current_ids();
+        let missing = path::Path::new("tests/fixtures/missing");
+        let mut format = long_format(SystemTime::now(), UserDb::new(missing, missing, uid, gid), true);
+        format.sizes = Some(DiskUsage::new(SizeMode::Apparent, false, Pool::new(1)));
+        format.columns = Column::defaults(true, false);
+        let src = Entry::new(&config, dir.join("src"), None);
+        let dirs = fs::metadata(dir.join("src")).unwrap().len() + fs::metadata(dir.join("src/bin")).unwrap().len();
+        assert!(format.format(&config, &src).contains(&format!(" {:>10} ", human_size(2048 + dirs))));
+        let main = dir.join("src/bin/main.rs");
+        let main = Entry::new(&config, main, None);
+        assert!(format.format(&config, &main).contains("   2.0 KiB "));
+    }
+}
+
+#[cfg(test)]
+mod get_attr_tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use std::os::unix::net::UnixListener;
+    use libc;
+    use self::super::super::defaults::load_user_config;
+    use self::super::super::testing::{calls_in, default_config, TempDir};
+    use self::super::super::IconSet;
+
+    fn attr_of(path: &path::Path) -> (Kind, ColorType, &'static str) {
+        let attr = get_attr(&default_config(), path);
+        (attr.kind, attr.color, attr.indicator)
+    }
+
+    #[test]
+    fn for_regular_files_and_dirs() {
+        let dir = TempDir::new("attr-regular");
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        fs::create_dir_all(dir.join("lib/lib.rs")).unwrap();
+        assert_eq!((Kind::File, ColorType::RecognizedFile, ""), attr_of(&dir.join("main.rs")));
+        assert_eq!((Kind::Dir, ColorType::Dir, "/"), attr_of(&dir.join("lib")));
+    }
+
+    #[test]
+    fn for_hidden_entries() {
+        let dir = TempDir::new("attr-hidden");
+        fs::create_dir_all(dir.join(".cache/pip")).unwrap();
+        fs::create_dir_all(dir.join(".git/objects")).unwrap();
+        fs::write(dir.join(".netrc"), "machine").unwrap();
+        fs::write(dir.join(".bashrc"), "set -o vi").unwrap();
+        let config = default_config();
+        let attr = |name: &str| {
+            let attr = get_attr(&config, &dir.join(name));
+            (attr.icon, attr.color)
+        };
+        assert_eq!((config.folders["@hidden"].clone(), ColorType::Hidden), attr(".cache"));
+        assert_eq!((config.folders[".git"].clone(), ColorType::Dir), attr(".git"));
+        assert_eq!((config.files["@hidden"].clone(), ColorType::Hidden), attr(".netrc"));
+        assert_eq!((config.files["shell"].clone(), ColorType::RecognizedFile), attr(".bashrc"));
+    }
+
+    #[test]
+    fn when_user_icons_are_minimal() {
+        let dir = TempDir::new("attr-minimal");
+        fs::create_dir_all(dir.join("list/.cache/pip")).unwrap();
+        fs::write(dir.join("list/.netrc"), "machine").unwrap();
+        fs::write(dir.join("folders.yaml"), "folder: F\n").unwrap();
+        fs::write(dir.join("files.yaml"), "file: f\n").unwrap();
+        let mut config = default_config();
+        load_user_config(&mut config, &dir.0, IconSet::Nerd).unwrap();
+        let attr = |name: &str| {
+            let attr = get_attr(&config, &dir.join("list").join(name));
+            (attr.icon, attr.color, attr.kind)
+        };
+        assert_eq!(("F".to_string(), ColorType::Hidden, Kind::Dir), attr(".cache"));
+        assert_eq!(("f".to_string(), ColorType::Hidden, Kind::File), attr(".netrc"));
+    }
+
+    #[test]
+    fn for_empty_entries() {
+        let dir = TempDir::new("attr-empty");
+        File::create(dir.join("empty.rs")).unwrap();
+        fs::write(dir.join("full.rs"), "fn main() {}").unwrap();
+        fs::create_dir(dir.join("void")).unwrap();
+        fs::create_dir(dir.join("lib")).unwrap();
+        fs::create_dir_all(dir.join("src/bin")).unwrap();
+        let config = default_config();
+        let attr = |name: &str| {
+            let entry = Entry::new(&config, dir.join(name), None);
+            (entry.attr.icon.clone(), entry.attr.color, entry.is_empty())
+        };
+        assert_eq!((config.files["rs"].clone(), ColorType::Empty, true), attr("empty.rs"));
+        assert_eq!((config.files["rs"].clone(), ColorType::RecognizedFile, false), attr("full.rs"));
+        assert_eq!((config.folders["@empty"].clone(), ColorType::Empty, true), attr("void"));
+        assert_eq!((config.folders["lib"].clone(), ColorType::Empty, true), attr("lib"));
+        assert_eq!((config.folders["folder"].clone(), ColorType::Dir, false), attr("src"));
+    }
+
+    #[test]
+    fn when_empty_is_not_shown() {
+        let dir = TempDir::new("attr-empty-unused");
+        fs::create_dir_all(dir.join("void")).unwrap();
+        fs::create_dir_all(dir.join("src/bin")).unwrap();
+        let mut config = default_config();
+        config.colors.remove(&ColorType::Empty);
+        config.folders.remove("@empty");
+        let void = Entry::new(&config, dir.join("void"), None);
+        let src = Entry::new(&config, dir.join("src"), None);
+        assert_eq!((ColorType::Dir, vec!["lstat", "lstat"]), (void.attr.color, calls_in(&dir.0)));
+        assert!(void.is_empty() && !src.is_empty() && void.is_empty());
+        assert_eq!(vec!["lstat", "lstat", "read_dir", "read_dir"], calls_in(&dir.0));
+    }
+
+    #[test]
+    fn for_executables() {
+        let dir = TempDir::new("attr-exec");
+        fs::write(dir.join("configure"), "exit 0").unwrap();
+        fs::set_permissions(dir.join("configure"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(dir.join("run.sh"), "exit 0").unwrap();
+        fs::set_permissions(dir.join("run.sh"), fs::Permissions::from_mode(0o744)).unwrap();
+        let config = default_config();
+        let attr = get_attr(&config, &dir.join("configure"));
+        assert_eq!((Kind::File, ColorType::Exec, "*"), (attr.kind, attr.color, attr.indicator));
+        assert_eq!(config.files["@exec"], attr.icon);
+        assert_eq!((Kind::File, ColorType::RecognizedFile, "*"), attr_of(&dir.join("run.sh")));
+    }
+
+    #[test]
+    fn when_named_like_special_keys() {
+        let dir = TempDir::new("attr-special-names");
+        fs::write(dir.join("foo.socket"), "[Socket]").unwrap();
+        fs::write(dir.join("x.@symlink"), "").unwrap();
+        fs::create_dir_all(dir.join("hidden/a")).unwrap();
+        fs::create_dir_all(dir.join("@sticky/a")).unwrap();
+        let config = default_config();
+        let attr = |name: &str| {
+            let attr = get_attr(&config, &dir.join(name));
+            (attr.key, attr.color, attr.recognized)
+        };
+        assert_eq!(("file".to_string(), ColorType::UnrecognizedFile, false), attr("foo.socket"));
+        assert_eq!(("file".to_string(), ColorType::Empty, false), attr("x.@symlink"));
+        assert_eq!(("folder".to_string(), ColorType::Dir, false), attr("hidden"));
+        assert_eq!(("folder".to_string(), ColorType::Dir, false), attr("@sticky"));
+    }
+
+    #[test]
+    fn when_sniffing_contents() {
+        let dir = TempDir::new("attr-sniff");
+        fs::write(dir.join("manage"), "#!/usr/bin/env python3\n").unwrap();
+        fs::write(dir.join("blob"), b"\x00\x01").unwrap();
+        fs::write(dir.join("tool"), b"\x7fELF\x02").unwrap();
+        fs::set_permissions(dir.join("tool"), fs::Permissions::from_mode(0o755)).unwrap();
+        let config = EntryConfig { sniff: true, ..default_config() };
+        let icon = |name: &str| get_attr(&config, &dir.join(name)).icon;
+        assert_eq!(config.files["py"], icon("manage"));
+        assert_eq!(config.files["binary"], icon("blob"));
+        assert_eq!(ColorType::Exec, get_attr(&config, &dir.join("tool")).color);
+        assert_eq!(config.files["file"], get_attr(&default_config(), &dir.join("manage")).icon);
+    }
+
+    #[test]
+    fn when_resolving_mime_types() {
+        let dir = TempDir::new("attr-mime");
+        fs::write(dir.join("photo.webp"), "RIFF").unwrap();
+        fs::write(dir.join("notes.txt"), "todo").unwrap();
+        fs::create_dir(dir.join("lib")).unwrap();
+        let db = MimeDb::parse(include_str!("../tests/fixtures/globs2"));
+        let config = EntryConfig { mime: Some(Arc::new(db)), ..default_config() };
+        let webp = get_attr(&config, &dir.join("photo.webp"));
+        assert_eq!((config.files["image"].clone(), ColorType::Image), (webp.icon, webp.color));
+        fs::write(dir.join("backup.tar.gz"), "gzip").unwrap();
+        fs::write(dir.join("clip.mp4"), "ftyp").unwrap();
+        assert_eq!(ColorType::Video, get_attr(&config, &dir.join("clip.mp4")).color);
+        assert_eq!(ColorType::Archive, get_attr(&config, &dir.join("backup.tar.gz")).color);
+        assert_eq!(ColorType::RecognizedFile, get_attr(&config, &dir.join("notes.txt")).color);
+        let themeless = EntryConfig { colors: HashMap::new(), ..config.clone() };
+        assert_eq!(ColorType::RecognizedFile, get_attr(&themeless, &dir.join("clip.mp4")).color);
+        assert_eq!(Some("image/webp".to_string()), webp.mime);
+        assert_eq!(Some("text/plain".to_string()), get_attr(&config, &dir.join("notes.txt")).mime);
+        assert_eq!(Some("inode/directory".to_string()), get_attr(&config, &dir.join("lib")).mime);
+        assert_eq!(None, get_attr(&default_config(), &dir.join("notes.txt")).mime);
+    }
+
+    #[test]
+    fn for_symlinks() {
+        let dir = TempDir::new("attr-symlink");
+        File::create(dir.join("target")).unwrap();
+        symlink(dir.join("target"), dir.join("link")).unwrap();
+        symlink(dir.join("missing"), dir.join("dead")).unwrap();
+        assert_eq!((Kind::Symlink, ColorType::Link, "@"), attr_of(&dir.join("link")));
+        assert_eq!((Kind::Symlink, ColorType::DeadLink, "@"), attr_of(&dir.join("dead")));
+    }
+
+    #[test]
+    fn for_fifos_and_sockets() {
+        let dir = TempDir::new("attr-special");
+        let fifo = CString::new(dir.join("fifo").to_str().unwrap()).unwrap();
+        assert_eq!(0, unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) });
+        let _listener = UnixListener::bind(dir.join("socket")).unwrap();
+        assert_eq!((Kind::Fifo, ColorType::Fifo, "|"), attr_of(&dir.join("fifo")));
+        assert_eq!((Kind::Socket, ColorType::Socket, "="), attr_of(&dir.join("socket")));
+    }
+
+    #[test]
+    fn for_mode_bits() {
+        let dir = TempDir::new("attr-modes");
+        File::create(dir.join("suid")).unwrap();
+        fs::set_permissions(dir.join("suid"), fs::Permissions::from_mode(0o4755)).unwrap();
+        fs::create_dir(dir.join("tmp")).unwrap();
+        fs::set_permissions(dir.join("tmp"), fs::Permissions::from_mode(0o1777)).unwrap();
+        fs::create_dir(dir.join("shared")).unwrap();
+        fs::set_permissions(dir.join("shared"), fs::Permissions::from_mode(0o777)).unwrap();
+        assert_eq!((Kind::Setuid, ColorType::Setuid, "*"), attr_of(&dir.join("suid")));
+        assert_eq!((Kind::Sticky, ColorType::Sticky, "/"), attr_of(&dir.join("tmp")));
+        assert_eq!((Kind::OtherWritable, ColorType::OtherWritable, "/"), attr_of(&dir.join("shared")));
+    }
+
+    #[test]
+    fn when_classifying_short_names() {
+        let dir = TempDir::new("attr-classify");
+        fs::create_dir(dir.join("lib")).unwrap();
+        let config = EntryConfig { classify: true, ..default_config() };
+        let entry = Entry::new(&config, dir.join("lib"), None);
+        assert_eq!(7, ShortFormat.predict(&config, &entry));
+        assert!(ShortFormat.format(&config, &entry).contains("lib/"));
+    }
+
+    #[test]
+    fn without_icons() {
+        let dir = TempDir::new("attr-no-icons");
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        let config = EntryConfig { width: 10, ..default_config() };
+        let entry = Entry::new(&config, dir.join("main.rs"), None);
+        let hidden = EntryConfig { icon_width: 0, ..config.clone() };
+        let wide = EntryConfig { icon_width: 2, ..config.clone() };
+        assert_eq!((10, 8, 11), (ShortFormat.predict(&config, &entry), ShortFormat.predict(&hidden, &entry), ShortFormat.predict(&wide, &entry)));
+        assert!(ShortFormat.format(&config, &entry).starts_with(&format!("{} ", config.files["rs"])));
+        assert!(ShortFormat.format(&hidden, &entry).starts_with(&format!("{}main.rs   ", color::Fg(color_for(&config, &ColorType::RecognizedFile)))));
+    }
+
+    #[test]
+    fn when_hyperlinking() {
+        use self::super::super::hyperlink::{file_url, hostname};
+        let dir = TempDir::new("attr-hyperlink");
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        let plain = EntryConfig { width: 20, ..default_config() };
+        let linked = EntryConfig { hyperlink: true, ..plain.clone() };
+        let entry = Entry::new(&plain, dir.join("main.rs"), None);
+        assert_eq!(ShortFormat.predict(&plain, &entry), ShortFormat.predict(&linked, &entry));
+        let url = format!("\x1b]8;;{}\x1b\\", file_url(hostname(), &dir.join("main.rs")));
+        let format = ShortFormat.format(&linked, &entry);
+        assert!(format.contains(&url));
+        assert_eq!(ShortFormat.format(&plain, &entry), format.replace(&url, "").replace("\x1b]8;;\x1b\\", ""));
+    }
+
+    #[test]
+    fn when_not_stating() {
+        let dir = TempDir::new("attr-no-stat");
+        fs::write(dir.join("configure"), "exit 0").unwrap();
+        fs::set_permissions(dir.join("configure"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::create_dir_all(dir.join("src/bin")).unwrap();
+        let config = EntryConfig { stat: false, ..default_config() };
+        let entries: Vec<Entry> = fs::read_dir(&dir.0).unwrap().map(|e| {
+            let e = e.unwrap();
+            Entry::new(&config, e.path(), e.file_type().ok())
+        }).collect();
+        for entry in &entries {
+            assert!(entry.meta.get().is_none());
+        }
+        let attr = |name: &str| {
+            let entry = entries.iter().find(|e| e.path.ends_with(name)).unwrap();
+            (entry.kind(), entry.attr.color, entry.attr.indicator)
+        };
+        assert_eq!((Kind::File, ColorType::UnrecognizedFile, ""), attr("configure"));
+        assert_eq!((Kind::Dir, ColorType::Dir, "/"), attr("src"));
+        assert_eq!(ColorType::Exec, Entry::new(&config, dir.join("configure"), None).attr.color);
+    }
+
+    #[test]
+    fn for_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+        use self::super::super::quoting::QuotingStyle;
+        let dir = TempDir::new("attr-non-utf8");
+        let path = dir.0.join(ffi::OsStr::from_bytes(b"bad\xff\n.rs"));
+        fs::write(&path, "fn main() {}").unwrap();
+        fs::create_dir_all(dir.0.join(ffi::OsStr::from_bytes(b".\xfe/cache"))).unwrap();
+        let config = EntryConfig { quoting: Quoting { style: QuotingStyle::ShellEscape, hide_control: false }, ..default_config() };
+        let entry = Entry::new(&config, path, None);
+        assert_eq!((config.files["rs"].clone(), ColorType::RecognizedFile), (entry.attr.icon.clone(), entry.attr.color));
+        assert!(ShortFormat.format(&config, &entry).contains("'bad'$'\\377\\n''.rs'"));
+        let hidden = Entry::new(&config, dir.0.join(ffi::OsStr::from_bytes(b".\xfe")), None);
+        assert_eq!((Kind::Dir, ColorType::Hidden), (hidden.kind(), hidden.attr.color));
+    }
+}
@@ -0,0 +1,70 @@
+use std::env;
+use std::os::unix::ffi::OsStrExt;
+use std::path;
+use std::sync::OnceLock;
+
+use libc;
+
+static HOSTNAME: OnceLock<String> = OnceLock::new();
+
+pub fn hostname() -> &'static str {
+    HOSTNAME.get_or_init(|| {
+        let mut buf = [0u8; 256];
+        let ok = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } == 0;
+        let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+        if ok { String::from_utf8_lossy(&buf[..len]).into_owned() } else { String::new() }
+    })
+}
+
+// Everything but unreserved characters and `/` is percent-encoded, byte by byte.
+fn encode(path: &path::Path) -> String {
+    let mut out = String::new();
+    for b in path.as_os_str().as_bytes() {
+        match *b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => out.push(*b as char),
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+// NOTE: Not canonicalized, so symlinks link to themselves rather than their targets.
+pub fn file_url(host: &str, path: &path::Path) -> String {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| path.to_path_buf())
+    };
+    format!("file://{}{}", host, encode(&absolute))
+}
+
+// OSC 8, which terminals without support print as just `text`.
+pub fn link(path: &path::Path, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", file_url(hostname(), path), text)
+}
+
+#[cfg(test)]
+mod hyperlink_tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn for_file_urls() {
+        assert_eq!("file://box/home/me/a%20b%23.rs", file_url("box", path::Path::new("/home/me/a b#.rs")));
+        assert_eq!("file:///tmp/za%C5%BC%FF", file_url("", &path::Path::new("/tmp").join(OsStr::from_bytes(b"za\xc5\xbc\xff"))));
+    }
+
+    #[test]
+    fn for_relative_paths() {
+        let url = file_url("box", path::Path::new("src/lib.rs"));
+        assert!(url.starts_with("file://box/"));
+        assert!(url.ends_with("/src/lib.rs"));
+    }
+
+    #[test]
+    fn for_escapes() {
+        let link = link(path::Path::new("/etc"), "etc");
+        assert!(link.starts_with("\x1b]8;;file://"));
+        assert!(link.ends_with("/etc\x1b\\etc\x1b]8;;\x1b\\"));
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::time::UNIX_EPOCH;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::{Serialize, Serializer};
+use serde_json;
+
+use self::super::colors::ColorType;
+use self::super::formatter::{Entry, Kind};
+use self::super::tabulator::{Config, Tabulator};
+
+const KINDS: [Kind; 11] = [
+    Kind::File, Kind::Dir, Kind::Symlink, Kind::Fifo, Kind::Socket, Kind::BlockDevice,
+    Kind::CharDevice, Kind::Setuid, Kind::Setgid, Kind::Sticky, Kind::OtherWritable,
+];
+
+impl Serialize for Kind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+struct KindVisitor;
+impl Visitor for KindVisitor {
+    type Value = Kind;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = KINDS.iter().map(Kind::name).collect();
+        write!(formatter, "one of {}", names.join(", "))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Kind, E>
+        where E: de::Error
+    {
+        KINDS.iter().find(|kind| kind.name() == value).cloned().ok_or_else(|| E::custom(format!("Unknown Kind: {}", value)))
+    }
+}
+
+impl Deserialize for Kind {
+    fn deserialize<D>(deserializer: D) -> Result<Kind, D::Error>
+        where D: Deserializer
+    {
+        deserializer.deserialize_str(KindVisitor)
+    }
+}
+
+// What lstat said, with times in seconds since the epoch.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct EntryMetadata {
+    pub size: u64,
+    // In 512-byte units, like st_blocks.
+    pub blocks: u64,
+    pub mode: u32,
+    pub nlink: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub dev: u64,
+    pub ino: u64,
+    pub modified: i64,
+    pub accessed: i64,
+    pub changed: i64,
+    // Not every filesystem records it.
+    pub created: Option<i64>,
+}
+
+impl From<&fs::Metadata> for EntryMetadata {
+    fn from(meta: &fs::Metadata) -> EntryMetadata {
+        EntryMetadata {
+            size: meta.len(),
+            blocks: meta.blocks(),
+            mode: meta.mode(),
+            nlink: meta.nlink(),
+            uid: meta.uid(),
+            gid: meta.gid(),
+            dev: meta.dev(),
+            ino: meta.ino(),
+            modified: meta.mtime(),
+            accessed: meta.atime(),
+            changed: meta.ctime(),
+            created: meta.created().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map(|time| time.as_secs() as i64),
+        }
+    }
+}
+
+// Everything colorls resolved about an entry, for other tools and --layout=json.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct EntryInfo {
+    // NOTE: Paths and names that are not UTF-8 are decoded lossily.
+    pub path: String,
+    pub name: String,
+    pub kind: Kind,
+    pub icon: String,
+    // Rule the icon came from, like `rs`, `image` or `folder`.
+    pub key: String,
+    pub color: ColorType,
+    pub indicator: String,
+    pub mime: Option<String>,
+    pub hidden: bool,
+    pub recognized: bool,
+    pub empty: bool,
+    pub metadata: Option<EntryMetadata>,
+}
+
+impl From<&Entry> for EntryInfo {
+    fn from(entry: &Entry) -> EntryInfo {
+        EntryInfo {
+            path: entry.path.to_string_lossy().into_owned(),
+            name: entry.name().to_string_lossy().into_owned(),
+            kind: entry.kind(),
+            icon: entry.icon().to_string(),
+            key: entry.key().to_string(),
+            color: entry.color(),
+            indicator: entry.indicator().to_string(),
+            mime: entry.mime().map(|mime| mime.to_string()),
+            hidden: entry.is_hidden(),
+            recognized: entry.is_recognized(),
+            empty: entry.is_empty(),
+            metadata: entry.metadata().map(EntryMetadata::from),
+        }
+    }
+}
+
+// A JSON array of EntryInfo, in place of the listing.
+#[derive(Debug)]
+pub struct JsonTabulator;
+impl Tabulator for JsonTabulator {
+    fn tabulate(&self, _config: &Config, names: Vec<Entry>) -> Vec<Vec<String>> {
+        let infos: Vec<EntryInfo> = names.iter().map(EntryInfo::from).collect();
+        vec![vec![serde_json::to_string_pretty(&infos).unwrap()]]
+    }
+}
+
+#[cfg(test)]
+mod info_tests {
+    use super::*;
+    use std::path;
+    use self::super::super::testing::{default_config, TempDir};
+
+    #[test]
+    fn for_round_trip() {
+        let dir = TempDir::new("info");
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        let config = default_config();
+        let info = EntryInfo::from(&Entry::new(&config, dir.join("main.rs"), None));
+        assert_eq!(("main.rs", Kind::File, "rs", ColorType::RecognizedFile), (&info.name[..], info.kind, &info.key[..], info.color));
+        assert_eq!(Some(12), info.metadata.as_ref().map(|meta| meta.size));
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(json.contains("\"kind\":\"file\"") && json.contains("\"color\":\"recognized_file\""));
+        assert_eq!(info, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn when_missing() {
+        let config = default_config();
+        let info = EntryInfo::from(&Entry::new(&config, path::PathBuf::from("tests/fixtures/missing"), None));
+        assert_eq!(None, info.metadata);
+        assert!(serde_json::from_str::<Kind>("\"folder\"").is_err());
+    }
+}
@@ -1,17 +1,62 @@
+// NOTE: serde_derive 0.9 wraps its impls in a `const _IMPL_...` block.
+#![allow(non_local_definitions)]
+
 extern crate termion;
 extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 extern crate unicode_segmentation;
 extern crate num_iter;
+extern crate libc;
+extern crate serde_yaml;
 
+use std::cmp::Reverse;
+use std::env;
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
 use std::path;
 use std::fs;
+use std::str::FromStr;
 
+mod cell;
+pub use self::cell::{Cell,Segment};
 mod colors;
 mod formatter;
-use self::formatter::{Entry,get_attr};
-pub use self::formatter::{Formatter,EntryConfig,ShortFormat,LongFormat};
+mod sniff;
+mod mime;
+mod users;
+mod quoting;
+mod hyperlink;
+pub use self::quoting::{Quoting,QuotingStyle};
+pub use self::users::UserDb;
+pub use self::mime::{MimeDb,DEFAULT_GLOBS};
+pub use self::formatter::{Formatter,Entry,EntryConfig,ShortFormat,LongFormat,Kind};
+mod info;
+pub use self::info::{EntryInfo,EntryMetadata,JsonTabulator};
+mod defaults;
+pub use self::defaults::{Theme,Overrides,LOCAL_CONFIG,USER_FILES,user_files,local_configs,user_config_dir,load_user_config,init_user_config};
+mod check;
+pub use self::check::{Problem,check_config};
+mod template;
+pub use self::template::{Template,TemplateFormat};
+mod table;
+pub use self::table::{Column,TableTabulator};
 mod tabulator;
-pub use self::tabulator::{Tabulator,Config,PlanningTabulator,NaiveTabulator,BinsearchTabulator,EntryOrder};
+pub use self::tabulator::{Tabulator,Config,PlanningTabulator,NaiveTabulator,BinsearchTabulator,OneLineTabulator,CommasTabulator,EntryOrder,Layout,GridAlgorithm};
+mod size;
+pub use self::size::{DiskUsage,SizeMode};
+mod pool;
+pub use self::pool::Pool;
+mod report;
+pub use self::report::{Report,ReportMode};
+mod timestamp;
+pub use self::timestamp::{TimeFormat,TimeStyle,TimeField,Zone};
+mod listing;
+pub use self::listing::ListingBuilder;
+#[cfg(test)]
+mod testing;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Verbosity {
@@ -20,37 +65,388 @@ pub enum Verbosity {
     Debug,
 }
 
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum When {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for When {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<When, String> {
+        match value {
+            "auto" => Ok(When::Auto),
+            "always" => Ok(When::Always),
+            "never" => Ok(When::Never),
+            _ => Err(format!("Unknown When: {}", value)),
+        }
+    }
+}
+
+impl When {
+    // Auto means only when writing to a terminal.
+    pub fn enabled(&self, tty: bool) -> bool {
+        match *self {
+            When::Auto => tty,
+            When::Always => true,
+            When::Never => false,
+        }
+    }
+}
+
+// Alternate files/folders maps, all with the same keys.
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IconSet {
+    // Private-use glyphs, needs a Nerd Font.
+    Nerd,
+    Emoji,
+    Ascii,
+}
+
+impl FromStr for IconSet {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<IconSet, String> {
+        match value {
+            "nerd" => Ok(IconSet::Nerd),
+            "emoji" => Ok(IconSet::Emoji),
+            "ascii" => Ok(IconSet::Ascii),
+            _ => Err(format!("Unknown IconSet: {}", value)),
+        }
+    }
+}
+
+impl IconSet {
+    // NOTE: Emoji are double width, the rest take one column.
+    pub fn width(&self) -> usize {
+        match *self {
+            IconSet::Emoji => 2,
+            IconSet::Nerd | IconSet::Ascii => 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Filter {
+    Empty,
+}
+
+impl Filter {
+    fn keep(&self, entry: &Entry) -> bool {
+        match *self {
+            Filter::Empty => entry.is_empty(),
+        }
+    }
+}
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Sort {
+    Name,
+    // Largest first.
+    Size,
+    // Newest first.
+    Time,
+    // As read from the directory.
+    None,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Sort, String> {
+        match value {
+            "name" => Ok(Sort::Name),
+            "size" => Ok(Sort::Size),
+            "time" => Ok(Sort::Time),
+            "none" => Ok(Sort::None),
+            _ => Err(format!("Unknown Sort: {}", value)),
+        }
+    }
+}
+
+impl Sort {
+    fn sort(&self, entries: &mut [Entry]) {
+        match *self {
+            Sort::Name => entries.sort_unstable(),
+            Sort::Size => {
+                let size = |entry: &Entry| Reverse(entry.metadata().map(|meta| meta.len()));
+                entries.sort_by(|a, b| size(a).cmp(&size(b)).then_with(|| a.cmp(b)))
+            }
+            Sort::Time => {
+                let time = |entry: &Entry| Reverse(entry.metadata().and_then(|meta| meta.modified().ok()));
+                entries.sort_by(|a, b| time(a).cmp(&time(b)).then_with(|| a.cmp(b)))
+            }
+            Sort::None => {}
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Source {
+    Directory(path::PathBuf),
+    // Listed as given, like the output of `fd` or `git ls-files`.
+    Paths(Vec<path::PathBuf>),
+}
+
+impl Source {
+    // Paths separated by `separator`, newline or NUL.
+    pub fn read<R: io::Read>(mut input: R, separator: u8) -> io::Result<Source> {
+        let mut bytes = vec![];
+        input.read_to_end(&mut bytes)?;
+        let paths = bytes.split(|byte| *byte == separator).filter(|path| !path.is_empty());
+        Ok(Source::Paths(paths.map(|path| path::PathBuf::from(OsStr::from_bytes(path))).collect()))
+    }
+}
+
+// What to show for a path from stdin: relative to the cwd where possible.
+fn relative(path: &path::Path, cwd: Option<&path::Path>) -> path::PathBuf {
+    let path = cwd.and_then(|cwd| path.strip_prefix(cwd).ok()).unwrap_or(path);
+    path.strip_prefix(".").unwrap_or(path).to_path_buf()
+}
+
 #[derive(Debug)]
 pub struct Action {
     pub verbosity: Verbosity,
-    pub directory: path::PathBuf,
+    pub source: Source,
     pub config: Config,
-    pub tabulator: Box<Tabulator>,
+    pub tabulator: Box<dyn Tabulator>,
+    pub filters: Vec<Filter>,
+    pub sort: Sort,
+    pub report: Option<ReportMode>,
+    pub pool: Pool,
+}
+
+impl Action {
+    // Entries of the source with their attributes, filtered and sorted.
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        let items: Vec<_> = match self.source {
+            Source::Directory(ref directory) => fs::read_dir(directory)?.map(|dir| {
+                dir.map(|dir| (dir.path(), dir.file_type().ok(), None))
+            }).collect::<io::Result<_>>()?,
+            Source::Paths(ref paths) => {
+                let cwd = env::current_dir().ok();
+                // NOTE: `.` and `..` (as printed by `find .`) have no name to pick an icon by.
+                paths.iter().filter(|path| path.file_name().is_some()).map(|path| {
+                    (path.clone(), None, Some(relative(path, cwd.as_deref()).into_os_string()))
+                }).collect()
+            }
+        };
+        let entry_config = &self.config.entry;
+        let mut ls : Vec<Entry> = self.pool.map(items, |(path, file_type, name)| {
+            let entry = Entry::new(entry_config, path, file_type);
+            match name {
+                Some(name) => entry.named(name),
+                None => entry,
+            }
+        });
+        ls.retain(|entry| self.filters.iter().all(|filter| filter.keep(entry)));
+        self.sort.sort(&mut ls);
+        Ok(ls)
+    }
+
+    // Bare names, each followed by `separator`: no icons, colors, quoting or padding.
+    pub fn write_names<W: io::Write>(&self, out: &mut W, separator: u8) -> io::Result<()> {
+        for entry in self.entries()? {
+            out.write_all(entry.name().as_bytes())?;
+            out.write_all(&[separator])?;
+        }
+        out.flush()
+    }
+
+    // What `run` prints, without printing it.
+    pub fn lines(&self) -> io::Result<Vec<String>> {
+        Ok(self.render(self.entries()?))
+    }
+
+    fn render(&self, ls: Vec<Entry>) -> Vec<String> {
+        let report = self.report.map(|mode| (mode, Report::new(&ls)));
+        let mut lines = match report {
+            Some((ReportMode::Json, ref report)) => return vec![report.to_json()],
+            Some((ReportMode::Only, _)) => vec![],
+            _ => self.tabulator.tabulate(&self.config, ls).into_iter().map(|items| items.concat()).collect(),
+        };
+        if let Some((mode, report)) = report {
+            if mode == ReportMode::Footer && !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(report.render(&self.config.entry));
+        }
+        lines
+    }
 }
 
 pub fn run(action : Action) {
     if action.verbosity != Verbosity::Quiet {
-        println!("Looking at {}", action.directory.display());
+        match action.source {
+            Source::Directory(ref directory) => println!("Looking at {}", directory.display()),
+            Source::Paths(ref paths) => println!("Looking at {} paths", paths.len()),
+        }
+
+    }
+    let ls = action.entries().unwrap();
+    // NOTE: Empty listings still get their report.
+    if ls.is_empty() && action.verbosity != Verbosity::Quiet {
+        println!("is empty.");
+    }
+    for line in action.render(ls) {
+        println!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod icon_set_tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use serde_yaml;
+
+    fn keys(yaml: &str) -> Vec<String> {
+        serde_yaml::from_str::<BTreeMap<String, String>>(yaml).unwrap().into_keys().collect()
+    }
+
+    #[test]
+    fn for_same_keys() {
+        let files = keys(include_str!("default_config/files.yaml"));
+        assert_eq!(files, keys(include_str!("default_config/files_emoji.yaml")));
+        assert_eq!(files, keys(include_str!("default_config/files_ascii.yaml")));
+        let folders = keys(include_str!("default_config/folders.yaml"));
+        assert_eq!(folders, keys(include_str!("default_config/folders_emoji.yaml")));
+        assert_eq!(folders, keys(include_str!("default_config/folders_ascii.yaml")));
+    }
+
+    #[test]
+    fn for_widths() {
+        assert_eq!(2, "emoji".parse::<IconSet>().unwrap().width());
+        assert_eq!(1, IconSet::Ascii.width());
+        assert!(When::Auto.enabled(true) && !When::Auto.enabled(false) && !When::Never.enabled(true));
+    }
+}
+
+#[cfg(test)]
+mod run_tests {
+    use super::*;
+    use std::env;
+    use std::process::Command;
+    use self::super::testing::{calls_in, default_config, TempDir};
+
+    const ENTRIES: usize = 25;
+
+    fn action(directory: path::PathBuf, mode: &str) -> Action {
+        let formatter: Box<dyn Formatter> = match mode {
+            "long" => Box::new(LongFormat {
+                time: TimeFormat::new(TimeStyle::Relative, TimeField::Modified),
+                columns: Column::defaults(false, false),
+                users: UserDb::system(),
+                numeric: false,
+                sizes: None,
+            }),
+            _ => Box::new(ShortFormat),
+        };
+        Action {
+            verbosity: Verbosity::Quiet,
+            source: Source::Directory(directory),
+            config: Config {
+                entry: EntryConfig { width: 100, stat: mode != "no-stat", ..default_config() },
+                max_width: 100,
+                formatter,
+                entry_order: EntryOrder::Vertical,
+            },
+            tabulator: Box::new(BinsearchTabulator),
+            filters: vec![],
+            sort: Sort::Name,
+            report: None,
+            pool: Pool::new(1),
+        }
+    }
 
+    #[test]
+    fn when_nothing_is_listed() {
+        let dir = TempDir::new("run-empty");
+        let mut action = action(dir.0.clone(), "short");
+        assert_eq!(Vec::<String>::new(), action.lines().unwrap());
+        action.tabulator = Layout::Json.tabulator();
+        assert_eq!(vec!["[]"], action.lines().unwrap());
+        action.tabulator = Layout::Grid.tabulator();
+        action.report = Some(ReportMode::Json);
+        assert!(action.lines().unwrap()[0].contains("\"files\": 0"));
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        action.report = Some(ReportMode::Footer);
+        action.filters = vec![Filter::Empty];
+        assert!(action.lines().unwrap()[0].contains("Found 0 entries, 0 B in total."));
     }
-    let dirs = fs::read_dir(action.directory).unwrap();
-    let config = action.config;
-    let mut ls : Vec<Entry> = dirs.map(|dir| {
-        let path = dir.unwrap().path();
-        Entry { path: path.clone(), attr: get_attr(&config.entry, &path) }
-    }).collect();
-    if ls.is_empty() {
-        if action.verbosity != Verbosity::Quiet {
-            println!("is empty.");
+
+    // Filesystem calls made for entries while listing `dir`, by call.
+    // NOTE: Only calls routed through `formatter::trace` are counted; for_stat_syscalls
+    // catches the rest when strace is around.
+    fn calls(dir: &TempDir, mode: &str, sort: Sort) -> (usize, usize) {
+        let before = calls_in(&dir.0).len();
+        let mut action = action(dir.0.clone(), mode);
+        action.sort = sort;
+        assert!(!action.lines().unwrap().is_empty());
+        let calls = &calls_in(&dir.0)[before..];
+        (calls.iter().filter(|&&call| call == "lstat").count(), calls.iter().filter(|&&call| call == "read_dir").count())
+    }
+
+    fn fixture(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        for i in 0..ENTRIES - 5 {
+            fs::write(dir.join(&format!("file-{}.rs", i)), "fn main() {}").unwrap();
         }
-        return
+        for i in 0..5 {
+            fs::create_dir_all(dir.join(&format!("dir-{}/src", i))).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn for_filesystem_calls() {
+        let dir = fixture("calls");
+        // read_dir already says what is a directory.
+        assert_eq!((0, 0), calls(&dir, "no-stat", Sort::Name));
+        assert_eq!((ENTRIES, 0), calls(&dir, "no-stat", Sort::Size));
+        // Every column shares one lstat per entry, and only directories are read.
+        assert_eq!((ENTRIES, 5), calls(&dir, "long", Sort::Name));
+        assert_eq!((ENTRIES, 5), calls(&dir, "short", Sort::Name));
     }
-    ls.sort_unstable();
-    let rows = action.tabulator.tabulate(&config, ls);
-    for items in rows {
-        for item in items {
-            print!("{}", item);
+
+    // Run under strace by for_stat_syscalls.
+    #[test]
+    #[ignore]
+    fn list_for_strace() {
+        if let (Ok(dir), Ok(mode)) = (env::var("COLORLS_STRACE_DIR"), env::var("COLORLS_STRACE_MODE")) {
+            run(action(path::PathBuf::from(dir), &mode));
         }
-        println!("");
+    }
+
+    // stat-family syscalls on paths inside `dir` while listing it.
+    fn stat_calls(dir: &TempDir, mode: &str) -> usize {
+        let log = dir.0.with_extension("strace");
+        let status = Command::new("strace")
+            .args(["-f", "-s", "4096", "-o"]).arg(&log)
+            .arg(env::current_exe().unwrap())
+            .args(["--exact", "run_tests::list_for_strace", "--ignored", "--nocapture"])
+            .env("COLORLS_STRACE_DIR", &dir.0)
+            .env("COLORLS_STRACE_MODE", mode)
+            .status()
+            .expect("strace is needed for this test");
+        assert!(status.success());
+        let trace = fs::read_to_string(&log).unwrap();
+        fs::remove_file(&log).unwrap();
+        let inside = format!("\"{}/", dir.0.display());
+        trace.lines().filter(|line| {
+            line.contains(&inside)
+                && ["stat(", "lstat(", "stat64(", "lstat64(", "fstatat64(", "newfstatat(", "statx("].iter().any(|call| line.contains(call))
+        }).count()
+    }
+
+    // NOTE: Needs strace, so it only runs with --ignored.
+    #[test]
+    #[ignore]
+    fn for_stat_syscalls() {
+        let dir = fixture("strace");
+        assert_eq!(0, stat_calls(&dir, "no-stat"));
+        assert!(stat_calls(&dir, "short") <= ENTRIES);
+        assert!(stat_calls(&dir, "long") <= ENTRIES);
     }
 }
@@ -0,0 +1,194 @@
+use std::path;
+
+use self::super::defaults::Theme;
+use self::super::formatter::{EntryConfig, Formatter, ShortFormat};
+use self::super::pool::Pool;
+use self::super::tabulator::{Config, Layout, Tabulator};
+use self::super::{Action, Filter, IconSet, Sort, Source, Verbosity};
+
+// An `Action` for embedding colorls: defaults from the embedded config,
+// nothing printed unless `run` is called on what it builds.
+#[derive(Debug)]
+pub struct ListingBuilder {
+    source: Source,
+    filters: Vec<Filter>,
+    sort: Sort,
+    layout: Layout,
+    tabulator: Option<Box<dyn Tabulator>>,
+    formatter: Box<dyn Formatter>,
+    icons: Option<IconSet>,
+    theme: Theme,
+    width: usize,
+    pool: Pool,
+}
+
+impl Default for ListingBuilder {
+    fn default() -> ListingBuilder {
+        ListingBuilder {
+            source: Source::Directory(path::PathBuf::from(".")),
+            filters: vec![],
+            sort: Sort::Name,
+            layout: Layout::Grid,
+            tabulator: None,
+            formatter: Box::new(ShortFormat),
+            icons: Some(IconSet::Nerd),
+            theme: Theme::Dark,
+            width: 80,
+            pool: Pool::default(),
+        }
+    }
+}
+
+impl ListingBuilder {
+    pub fn new() -> ListingBuilder {
+        ListingBuilder::default()
+    }
+
+    pub fn path<P: Into<path::PathBuf>>(mut self, directory: P) -> ListingBuilder {
+        self.source = Source::Directory(directory.into());
+        self
+    }
+
+    // Lists exactly these, shown relative to the cwd, instead of a directory.
+    pub fn paths(mut self, paths: Vec<path::PathBuf>) -> ListingBuilder {
+        self.source = Source::Paths(paths);
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> ListingBuilder {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn sort(mut self, sort: Sort) -> ListingBuilder {
+        self.sort = sort;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> ListingBuilder {
+        self.layout = layout;
+        self
+    }
+
+    // Replaces the layout's tabulator, like TableTabulator for LongFormat.
+    pub fn tabulator(mut self, tabulator: Box<dyn Tabulator>) -> ListingBuilder {
+        self.tabulator = Some(tabulator);
+        self
+    }
+
+    pub fn formatter(mut self, formatter: Box<dyn Formatter>) -> ListingBuilder {
+        self.formatter = formatter;
+        self
+    }
+
+    // None leaves out icons and their space.
+    pub fn icons(mut self, icons: Option<IconSet>) -> ListingBuilder {
+        self.icons = icons;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> ListingBuilder {
+        self.theme = theme;
+        self
+    }
+
+    pub fn width(mut self, width: usize) -> ListingBuilder {
+        self.width = width;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> ListingBuilder {
+        self.pool = Pool::new(threads);
+        self
+    }
+
+    pub fn build(self) -> Action {
+        let layout = self.layout;
+        let entry = EntryConfig {
+            width: self.width,
+            icon_width: self.icons.map(|icons| icons.width()).unwrap_or(0),
+            ..EntryConfig::defaults(self.icons.unwrap_or(IconSet::Nerd), self.theme)
+        };
+        Action {
+            verbosity: Verbosity::Quiet,
+            source: self.source,
+            config: Config {
+                entry,
+                max_width: self.width,
+                formatter: self.formatter,
+                entry_order: layout.entry_order(),
+            },
+            tabulator: self.tabulator.unwrap_or_else(|| layout.tabulator()),
+            filters: self.filters,
+            sort: self.sort,
+            report: None,
+            pool: self.pool,
+        }
+    }
+}
+
+#[cfg(test)]
+mod listing_tests {
+    use super::*;
+    use std::env;
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+    use self::super::super::testing::TempDir;
+
+    fn dir() -> TempDir {
+        let dir = TempDir::new("listing");
+        fs::write(dir.join("a.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("b.md"), "# colorls, but longer").unwrap();
+        fs::create_dir(dir.join("c")).unwrap();
+        dir
+    }
+
+    fn plain(line: &str) -> String {
+        line.split('\x1b').enumerate().map(|(i, part)| if i == 0 { part } else { &part[part.find('m').unwrap() + 1..] }).collect()
+    }
+
+    #[test]
+    fn for_lines() {
+        let dir = dir();
+        let listing = ListingBuilder::new().path(&dir.0).layout(Layout::Commas).icons(None).build();
+        let lines: Vec<String> = listing.lines().unwrap().iter().map(|line| plain(line)).collect();
+        assert_eq!(vec!["a.rs, b.md, c"], lines);
+    }
+
+    #[test]
+    fn for_entries() {
+        let dir = dir();
+        let listing = ListingBuilder::new().path(&dir.0).filter(Filter::Empty).threads(1).build();
+        assert_eq!(vec![dir.join("c")], listing.entries().unwrap().into_iter().map(|entry| entry.path).collect::<Vec<_>>());
+        let listing = ListingBuilder::new().path(&dir.0).sort(Sort::Size).build();
+        let names: Vec<_> = listing.entries().unwrap().into_iter().filter(|entry| !entry.kind().is_dir()).map(|entry| entry.path).collect();
+        assert_eq!(vec![dir.join("b.md"), dir.join("a.rs")], names);
+    }
+
+    #[test]
+    fn for_paths() {
+        let cwd = env::current_dir().unwrap();
+        let paths = vec![path::PathBuf::from("./Cargo.toml"), cwd.join("src/lib.rs"), path::PathBuf::from("."), path::PathBuf::from("src")];
+        let listing = ListingBuilder::new().paths(paths).sort(Sort::None).build();
+        let names: Vec<_> = listing.entries().unwrap().iter().map(|entry| entry.name().to_os_string()).collect();
+        assert_eq!(vec!["Cargo.toml", "src/lib.rs", "src"], names);
+        let paths = vec![path::PathBuf::from("a.rs"), path::PathBuf::from("b c.rs")];
+        assert_eq!(Source::Paths(paths), Source::read(&b"a.rs\0b c.rs\0\0"[..], 0).unwrap());
+    }
+
+    #[test]
+    fn for_names() {
+        let dir = dir();
+        fs::write(dir.0.join(OsStr::from_bytes(b"bad\xff\n.rs")), "").unwrap();
+        let listing = ListingBuilder::new().path(&dir.0).filter(Filter::Empty).build();
+        let mut out = vec![];
+        listing.write_names(&mut out, 0).unwrap();
+        assert_eq!(b"bad\xff\n.rs\0c\0".to_vec(), out);
+    }
+
+    #[test]
+    fn when_missing() {
+        assert!(ListingBuilder::new().path("tests/fixtures/missing").build().lines().is_err());
+    }
+}
@@ -1,13 +1,15 @@
+#[macro_use]
 extern crate clap;
-use clap::{Arg, App};
+use clap::{Arg, App, SubCommand};
 
 extern crate termion;
 use termion::terminal_size;
 
-extern crate serde_yaml;
-
 use std::env;
+use std::io;
 use std::path;
+use std::process;
+use std::sync::Arc;
 
 extern crate colorls;
 use colorls::*;
@@ -17,78 +19,441 @@ fn main() {
         .version("0.1.2")
         .author("scoiatael <czapl.luk+git@gmail.com>")
         .about("List information about the FILEs (the current directory by default).")
+        .arg(Arg::with_name("layout")
+             .long("layout")
+             .takes_value(true)
+             .value_name("WORD")
+             .possible_values(&["grid", "across", "oneline", "commas", "json"])
+             .overrides_with_all(&["grid", "entry order", "oneline", "commas"])
+             .help("Arrange entries in columns (grid, across) one per line (oneline, the default when not on a terminal), separated by commas or as JSON"))
+        .arg(Arg::with_name("grid")
+             .short("C")
+             .overrides_with_all(&["layout", "entry order", "oneline", "commas"])
+             .help("list entries by columns, like --layout=grid"))
         .arg(Arg::with_name("entry order")
              .short("x")
-             .help("list entries by lines instead of by columns"))
+             .overrides_with_all(&["layout", "grid", "oneline", "commas"])
+             .help("list entries by lines instead of by columns, like --layout=across"))
+        .arg(Arg::with_name("oneline")
+             .short("1")
+             .overrides_with_all(&["layout", "grid", "entry order", "commas"])
+             .help("list one entry per line, like --layout=oneline"))
+        .arg(Arg::with_name("commas")
+             .short("m")
+             .overrides_with_all(&["layout", "grid", "entry order", "oneline"])
+             .help("list entries separated by commas, like --layout=commas"))
         .arg(Arg::with_name("long")
              .long("long")
              .short("l")
              .help("Prints using long format"))
-        .arg(Arg::with_name("naive")
-             .long("naive")
+        .arg(Arg::with_name("columns")
+             .long("columns")
+             .takes_value(true)
+             .value_name("LIST")
+             .use_delimiter(true)
+             .require_equals(true)
+             .help("Columns of the long format, in order: perms, size, user, group, time, mtime, atime, ctime, btime, mime, icon, name"))
+        .arg(Arg::with_name("no-user")
+             .long("no-user")
+             .help("Hide the user column of the long format"))
+        .arg(Arg::with_name("no-group")
+             .long("no-group")
+             .help("Hide the group column of the long format"))
+        .arg(Arg::with_name("no-time")
+             .long("no-time")
+             .help("Hide the time columns of the long format"))
+        .arg(Arg::with_name("format-string")
+             .long("format-string")
+             .takes_value(true)
+             .value_name("TEMPLATE")
+             .help("Print each entry through a template like '{icon} {name:color} {size:h:>10} {mtime:relative}'. \
+                    Fields: icon, name, path, indicator, key, kind, mime, size, user, group, uid, gid, mtime, atime, ctime, btime. \
+                    Modifiers: <N, >N, ^N to align, color, h (sizes), a time style or +FORMAT (times)"))
+        .arg(Arg::with_name("classify")
+             .long("classify")
+             .short("F")
+             .help("Append indicator (one of */=@|) to entries"))
+        .arg(Arg::with_name("sniff")
+             .long("sniff")
+             .help("Detect type of extensionless files from their contents"))
+        .arg(Arg::with_name("mime")
+             .long("mime")
+             .help("Resolve MIME types and show them in long format"))
+        .arg(Arg::with_name("mime-db")
+             .long("mime-db")
+             .takes_value(true)
+             .value_name("PATH")
+             .help("Resolve MIME types with this shared-mime-info globs2 file"))
+        .arg(Arg::with_name("quoting-style")
+             .long("quoting-style")
+             .takes_value(true)
+             .value_name("WORD")
+             .possible_values(&["literal", "shell", "shell-escape", "c", "escape"])
+             .help("Quote entry names with this style"))
+        .arg(Arg::with_name("escape")
+             .long("escape")
+             .short("b")
+             .help("Print C-style escapes for nongraphic characters, like --quoting-style=escape"))
+        .arg(Arg::with_name("hide-control-chars")
+             .long("hide-control-chars")
+             .short("q")
+             .help("Print ? instead of nongraphic characters (the default on a terminal)"))
+        .arg(Arg::with_name("show-control-chars")
+             .long("show-control-chars")
+             .overrides_with("hide-control-chars")
+             .help("Print nongraphic characters as-is"))
+        .arg(Arg::with_name("hyperlink")
+             .long("hyperlink")
+             .takes_value(true)
+             .min_values(0)
+             .require_equals(true)
+             .value_name("WHEN")
+             .possible_values(&["auto", "always", "never"])
+             .help("Link entry names to their files (OSC 8): auto (only on a terminal), always (the default with no WHEN) or never"))
+        .arg(Arg::with_name("icons")
+             .long("icons")
+             .takes_value(true)
+             .value_name("WHEN")
+             .default_value("auto")
+             .possible_values(&["auto", "always", "never"])
+             .help("Show icons: auto (only on a terminal), always or never"))
+        .arg(Arg::with_name("no-icons")
+             .long("no-icons")
+             .help("Do not show icons, like --icons=never"))
+        .arg(Arg::with_name("icon-set")
+             .long("icon-set")
+             .takes_value(true)
+             .value_name("SET")
+             .default_value("nerd")
+             .possible_values(&["nerd", "emoji", "ascii"])
+             .help("Icons to use: nerd (needs a Nerd Font), emoji or ascii"))
+        .arg(Arg::with_name("no-stat")
+             .long("no-stat")
+             .help("Do not stat entries in short format, losing executable, permission bit, dead link and empty highlights"))
+        .arg(Arg::with_name("empty")
+             .long("empty")
+             .help("List only empty files and directories"))
+        .arg(Arg::with_name("numeric-uid-gid")
+             .long("numeric-uid-gid")
              .short("n")
-             .multiple(true)
-             .help("Prints using naive tabulator"))
+             .help("List numeric user and group IDs in long format"))
+        .arg(Arg::with_name("total-size")
+             .long("total-size")
+             .takes_value(true)
+             .min_values(0)
+             .require_equals(true)
+             .value_name("MODE")
+             .possible_values(&["apparent", "disk"])
+             .help("Show sizes in long format, directories with everything below them (apparent, the default, or disk usage)"))
+        .arg(Arg::with_name("one-file-system")
+             .long("one-file-system")
+             .help("Do not cross filesystem boundaries when computing --total-size"))
+        .arg(Arg::with_name("report")
+             .long("report")
+             .takes_value(true)
+             .min_values(0)
+             .require_equals(true)
+             .value_name("MODE")
+             .possible_values(&["footer", "only", "json"])
+             .help("Summarize the listing: after it (footer, the default), instead of it (only) or as JSON"))
+        .arg(Arg::with_name("time-style")
+             .long("time-style")
+             .takes_value(true)
+             .value_name("STYLE")
+             .default_value("relative")
+             .help("Time format in long format: relative, iso, long-iso, full-iso or +FORMAT (strftime)"))
+        .arg(Arg::with_name("time")
+             .long("time")
+             .takes_value(true)
+             .value_name("WORD")
+             .default_value("modified")
+             .possible_values(&["modified", "accessed", "changed", "created", "mtime", "atime", "ctime", "birth"])
+             .help("Which timestamp to show in long format"))
+        .arg(Arg::with_name("sort")
+             .long("sort")
+             .takes_value(true)
+             .value_name("WORD")
+             .default_value("name")
+             .possible_values(&["name", "size", "time", "none"])
+             .help("Sort by name, size (largest first), time (newest first) or not at all"))
+        .arg(Arg::with_name("threads")
+             .long("threads")
+             .takes_value(true)
+             .value_name("N")
+             .help("Collect entry metadata and --total-size totals on N threads (defaults to the number of CPUs)"))
+        .arg(Arg::with_name("grid-algorithm")
+             .long("grid-algorithm")
+             .takes_value(true)
+             .value_name("ALGORITHM")
+             .default_value("binsearch")
+             .possible_values(&["binsearch", "planning", "naive"])
+             .help("How grid and across layouts fit names into columns"))
         .arg(Arg::with_name("verbose")
              .short("v")
              .long("verbose")
              .multiple(true)
              .help("Sets the level of verbosity"))
+        .arg(Arg::with_name("no-local-config")
+             .long("no-local-config")
+             .help("Ignore .colorls.yaml files in the listed directory and its parents up to the repository root"))
+        .arg(Arg::with_name("print0")
+             .long("print0")
+             .help("Print bare names separated by NUL, without icons, colors, quoting or padding, for xargs -0"))
+        .arg(Arg::with_name("stdin")
+             .long("stdin")
+             .conflicts_with("FILE")
+             .help("List the paths read from standard input, one per line, instead of a directory"))
+        .arg(Arg::with_name("null")
+             .short("0")
+             .requires("stdin")
+             .help("Paths on standard input are separated by NUL, like find -print0"))
         .arg(Arg::with_name("FILE")
              .required(false)
              .index(1))
+        .subcommand(SubCommand::with_name("config")
+             .about("Inspect the icons, aliases and colors colorls uses (list a directory named config as ./config)")
+             .subcommand(SubCommand::with_name("check")
+                  .about("Parse every config file and report unknown names, broken aliases and missing fallbacks"))
+             .subcommand(SubCommand::with_name("dump")
+                  .about("Print the merged effective config, as YAML"))
+             .subcommand(SubCommand::with_name("init")
+                  .about("Write the embedded defaults into the user config dir, keeping files already there")))
         .get_matches();
 
     let verbosity = match matches.occurrences_of("v") {
         0 => Verbosity::Quiet,
         1 => Verbosity::Warn,
-        2 | _ =>  Verbosity::Debug,
+        _ =>  Verbosity::Debug,
     };
-    let tabulator : Box<Tabulator> = match matches.occurrences_of("naive") {
-        0 => Box::new(BinsearchTabulator),
-        1 => Box::new(PlanningTabulator),
-        _ => Box::new(NaiveTabulator),
+    let tty = termion::is_tty(&io::stdout());
+    let layout = if matches.is_present("oneline") {
+        Layout::OneLine
+    } else if matches.is_present("commas") {
+        Layout::Commas
+    } else if matches.is_present("entry order") {
+        Layout::Across
+    } else if matches.is_present("grid") {
+        Layout::Grid
+    } else if matches.is_present("layout") {
+        value_t!(matches, "layout", Layout).unwrap_or_else(|e| e.exit())
+    } else if tty {
+        Layout::Grid
+    } else {
+        Layout::OneLine
     };
-    let formatter : Box<Formatter> = match matches.occurrences_of("long") {
-        0 => Box::new(ShortFormat),
-        1 | _ =>  Box::new(LongFormat),
+    let pool = if matches.is_present("threads") {
+        Pool::new(value_t!(matches, "threads", usize).unwrap_or_else(|e| e.exit()))
+    } else {
+        Pool::default()
     };
-    let entry_order = match matches.occurrences_of("entry order") {
-        0 => EntryOrder::Vertical,
-        1 | _ => EntryOrder::Horizontal,
+    let formatter : Box<dyn Formatter> = match matches.occurrences_of("long") {
+        _ if matches.is_present("format-string") => {
+            let style = value_t!(matches, "time-style", TimeStyle).unwrap_or_else(|e| e.exit());
+            Box::new(TemplateFormat {
+                template: matches.value_of("format-string").unwrap().parse().unwrap_or_else(|e: String| {
+                    clap::Error::with_description(&format!("Invalid --format-string: {}", e), clap::ErrorKind::InvalidValue).exit()
+                }),
+                time: TimeFormat::new(style, TimeField::Modified),
+                users: UserDb::system(),
+            })
+        }
+        0 => Box::new(ShortFormat),
+        _ =>  {
+            let style = value_t!(matches, "time-style", TimeStyle).unwrap_or_else(|e| e.exit());
+            let field = value_t!(matches, "time", TimeField).unwrap_or_else(|e| e.exit());
+            let mut columns = match matches.values_of("columns") {
+                Some(values) => values.map(|value| value.parse().unwrap_or_else(|e: String| {
+                    clap::Error::with_description(&format!("Invalid --columns: {}", e), clap::ErrorKind::InvalidValue).exit()
+                })).collect(),
+                None => Column::defaults(matches.is_present("total-size"), matches.is_present("mime")),
+            };
+            columns.retain(|column| match *column {
+                Column::User => !matches.is_present("no-user"),
+                Column::Group => !matches.is_present("no-group"),
+                Column::Time(_) => !matches.is_present("no-time"),
+                _ => true,
+            });
+            Box::new(LongFormat {
+                time: TimeFormat::new(style, field),
+                columns,
+                users: UserDb::system(),
+                numeric: matches.is_present("numeric-uid-gid"),
+                sizes: if matches.is_present("total-size") {
+                    let mode = matches.value_of("total-size").unwrap_or("apparent").parse().unwrap_or(SizeMode::Apparent);
+                    Some(DiskUsage::new(mode, matches.is_present("one-file-system"), pool))
+                } else {
+                    None
+                },
+            })
+        }
     };
 
-    let file_icons = serde_yaml::from_str(include_str!("default_config/files.yaml")).unwrap();
-    let folder_icons = serde_yaml::from_str(include_str!("default_config/folders.yaml")).unwrap();
-    let file_aliases = serde_yaml::from_str(include_str!("default_config/file_aliases.yaml")).unwrap();
-    let folder_aliases = serde_yaml::from_str(include_str!("default_config/folder_aliases.yaml")).unwrap();
-    let colors = serde_yaml::from_str(include_str!("default_config/dark_colors.yaml")).unwrap();
-    let cdir_path = env::current_dir().unwrap();
-    let dir = matches.value_of("FILE").unwrap_or_else(|| cdir_path.to_str().unwrap());
-    let path = path::PathBuf::from(dir);
-    let width = terminal_size().unwrap().0 as usize;
+    let icon_set = value_t!(matches, "icon-set", IconSet).unwrap_or_else(|e| e.exit());
+    let icons = value_t!(matches, "icons", When).unwrap_or_else(|e| e.exit());
+    let icon_width = if !matches.is_present("no-icons") && icons.enabled(tty) {
+        icon_set.width()
+    } else {
+        0
+    };
+    let mut defaults = EntryConfig::defaults(icon_set, Theme::Dark);
+    let user_dir = user_config_dir();
+    if let Some(ref dir) = user_dir {
+        if let Err(e) = load_user_config(&mut defaults, dir, icon_set) {
+            eprintln!("colorls: ignoring user config: {}", e);
+        }
+    }
+    if let Some(config) = matches.subcommand_matches("config") {
+        let cwd = env::current_dir().unwrap();
+        let local = if matches.is_present("no-local-config") { vec![] } else { local_configs(&cwd) };
+        match config.subcommand_name() {
+            Some("check") => {
+                let (files, problems) = check_config(icon_set, user_dir.as_deref(), &local);
+                for problem in &problems {
+                    println!("{}", problem);
+                }
+                if !problems.is_empty() {
+                    process::exit(1);
+                }
+                println!("{} files OK", files);
+            }
+            Some("dump") => {
+                for path in local {
+                    match Overrides::load(&path) {
+                        Ok(overrides) => defaults.merge(overrides),
+                        Err(e) => eprintln!("colorls: ignoring {}: {}", path.display(), e),
+                    }
+                }
+                print!("{}", defaults.dump());
+            }
+            Some("init") => {
+                let dir = user_dir.unwrap_or_else(|| {
+                    clap::Error::with_description("Cannot find the user config dir, set XDG_CONFIG_HOME or HOME", clap::ErrorKind::Io).exit()
+                });
+                let written = init_user_config(&dir).unwrap_or_else(|e| {
+                    clap::Error::with_description(&format!("Cannot write {}: {}", dir.display(), e), clap::ErrorKind::Io).exit()
+                });
+                for &(name, _) in &USER_FILES {
+                    let path = dir.join(name);
+                    println!("{} {}", if written.contains(&path) { "wrote" } else { "kept" }, path.display());
+                }
+            }
+            _ => clap::Error::with_description("Expected one of check, dump or init", clap::ErrorKind::MissingSubcommand).exit(),
+        }
+        return;
+    }
+    let mime = if matches.is_present("mime") || matches.is_present("mime-db") {
+        let globs = path::Path::new(matches.value_of("mime-db").unwrap_or(DEFAULT_GLOBS));
+        match MimeDb::load(globs) {
+            Ok(db) => Some(Arc::new(db)),
+            Err(e) => {
+                eprintln!("colorls: cannot read {}: {}", globs.display(), e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let mut filters = vec![];
+    if matches.is_present("empty") {
+        filters.push(Filter::Empty);
+    }
+    let report = if matches.is_present("report") {
+        Some(matches.value_of("report").unwrap_or("footer").parse().unwrap_or(ReportMode::Footer))
+    } else {
+        None
+    };
+    let quoting = Quoting {
+        style: if matches.is_present("escape") {
+            QuotingStyle::Escape
+        } else if matches.is_present("quoting-style") {
+            value_t!(matches, "quoting-style", QuotingStyle).unwrap_or_else(|e| e.exit())
+        } else {
+            QuotingStyle::Literal
+        },
+        hide_control: matches.is_present("hide-control-chars")
+            || (!matches.is_present("show-control-chars") && tty),
+    };
+    let hyperlink = match matches.value_of("hyperlink") {
+        Some(when) => when.parse().unwrap_or(When::Never),
+        None if matches.is_present("hyperlink") => When::Always,
+        None => When::Never,
+    };
+    let source = if matches.is_present("stdin") {
+        let separator = if matches.is_present("null") { b'\0' } else { b'\n' };
+        Source::read(io::stdin(), separator).unwrap_or_else(|e| {
+            clap::Error::with_description(&format!("Cannot read paths from standard input: {}", e), clap::ErrorKind::Io).exit()
+        })
+    } else {
+        match matches.value_of_os("FILE") {
+            Some(dir) => Source::Directory(path::PathBuf::from(dir)),
+            None => Source::Directory(env::current_dir().unwrap()),
+        }
+    };
+    // NOTE: Paths from stdin may come from anywhere, so only directories get local config.
+    if let Source::Directory(ref directory) = source {
+        if !matches.is_present("no-local-config") {
+            for path in local_configs(directory) {
+                match Overrides::load(&path) {
+                    Ok(overrides) => defaults.merge(overrides),
+                    Err(e) => eprintln!("colorls: ignoring {}: {}", path.display(), e),
+                }
+            }
+        }
+    }
+    // NOTE: Without a terminal, COLUMNS is all there is to go by.
+    let width = match terminal_size() {
+        Ok((width, _)) => width as usize,
+        Err(_) => env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).unwrap_or(80),
+    };
     let action = Action {
-        directory: path,
+        source,
         config: Config {
             max_width: width,
             entry: EntryConfig {
-                files: file_icons,
-                folders: folder_icons,
-                file_aliases,
-                colors,
-                folder_aliases,
                 width,
+                classify: matches.is_present("classify"),
+                sniff: matches.is_present("sniff"),
+                mime,
+                // NOTE: Colors and icons need the mode bits, so only --no-stat short listings
+                // go by read_dir's file type alone; -F and long format stat regardless.
+                stat: !matches.is_present("no-stat") || matches.is_present("classify") || matches.is_present("long"),
+                quoting,
+                hyperlink: hyperlink.enabled(tty),
+                icon_width,
+                ..defaults
             },
-            entry_order,
+            entry_order: layout.entry_order(),
             formatter,
         },
         verbosity,
-        tabulator,
+        // NOTE: The long format is a table, whatever the layout.
+        tabulator: if matches.is_present("long") && !matches.is_present("format-string") && layout != Layout::Json {
+            Box::new(TableTabulator)
+        } else {
+            match layout {
+                Layout::Grid | Layout::Across => value_t!(matches, "grid-algorithm", GridAlgorithm).unwrap_or_else(|e| e.exit()).tabulator(),
+                _ => layout.tabulator(),
+            }
+        },
+        filters,
+        sort: value_t!(matches, "sort", Sort).unwrap_or_else(|e| e.exit()),
+        report,
+        pool,
     };
 
     if verbosity == Verbosity::Debug {
         println!("{:?}", action);
 
     }
+    if matches.is_present("print0") {
+        // NOTE: A closed pipe (like `| head -z`) is not worth reporting.
+        if let Err(e) = action.write_names(&mut io::stdout().lock(), b'\0') {
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                eprintln!("colorls: {}", e);
+                process::exit(2);
+            }
+        }
+        return;
+    }
     run(action);
 }
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path;
+
+pub const DEFAULT_GLOBS: &str = "/usr/share/mime/globs2";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Glob {
+    weight: u32,
+    mime: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+// Parsed freedesktop shared-mime-info glob database (globs2 format).
+#[derive(Default)]
+pub struct MimeDb {
+    literals: HashMap<String, Glob>,
+    // Keyed by suffix including the dot, for the common `*.ext` patterns.
+    suffixes: HashMap<String, Glob>,
+    globs: Vec<Glob>,
+}
+
+impl fmt::Debug for MimeDb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MimeDb {{ {} globs }}", self.literals.len() + self.suffixes.len() + self.globs.len())
+    }
+}
+
+fn has_wildcards(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+fn better<'a>(a: Option<&'a Glob>, b: &'a Glob) -> Option<&'a Glob> {
+    match a {
+        Some(a) if (a.weight, a.pattern.len()) >= (b.weight, b.pattern.len()) => Some(a),
+        _ => Some(b),
+    }
+}
+
+impl MimeDb {
+    pub fn load(path: &path::Path) -> io::Result<MimeDb> {
+        Ok(MimeDb::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(text: &str) -> MimeDb {
+        let mut db = MimeDb::default();
+        for line in text.lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 3 {
+                continue;
+            }
+            let weight = match fields[0].parse() {
+                Ok(weight) => weight,
+                Err(_) => continue,
+            };
+            let case_sensitive = fields.get(3).map(|flags| flags.split(',').any(|f| f == "cs")).unwrap_or(false);
+            let pattern = if case_sensitive { fields[2].to_string() } else { fields[2].to_lowercase() };
+            let glob = Glob { weight, mime: fields[1].to_string(), pattern, case_sensitive };
+            // NOTE: Files are sorted by weight, so the first rule for a pattern wins.
+            if !has_wildcards(&glob.pattern) {
+                db.literals.entry(glob.pattern.clone()).or_insert(glob);
+            } else if glob.pattern.starts_with("*.") && !has_wildcards(&glob.pattern[1..]) {
+                db.suffixes.entry(glob.pattern[1..].to_string()).or_insert(glob);
+            } else {
+                db.globs.push(glob);
+            }
+        }
+        db
+    }
+
+    pub fn lookup(&self, file_name: &str) -> Option<&str> {
+        let lower = file_name.to_lowercase();
+        let name_for = |glob: &Glob| if glob.case_sensitive { file_name } else { &lower[..] };
+        for key in &[file_name, &lower[..]] {
+            if let Some(glob) = self.literals.get(*key) {
+                if name_for(glob) == *key {
+                    return Some(&glob.mime);
+                }
+            }
+        }
+        let mut best = None;
+        // NOTE: Lowercasing can change byte lengths, so each name finds its own dots.
+        for name in &[file_name, &lower[..]] {
+            for (i, _) in name.match_indices('.') {
+                if let Some(glob) = self.suffixes.get(&name[i..]) {
+                    if name_for(glob).ends_with(&name[i..]) {
+                        best = better(best, glob);
+                    }
+                }
+            }
+        }
+        for glob in &self.globs {
+            if fnmatch(glob.pattern.as_bytes(), name_for(glob).as_bytes()) {
+                best = better(best, glob);
+            }
+        }
+        best.map(|glob| &glob.mime[..])
+    }
+}
+
+// Shell-style matching with `*`, `?` and `[...]`, which is all globs2 uses.
+fn fnmatch(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some(&b'*') => (0..=name.len()).any(|i| fnmatch(&pattern[1..], &name[i..])),
+        Some(&b'?') => !name.is_empty() && fnmatch(&pattern[1..], &name[1..]),
+        Some(&b'[') => {
+            let end = match pattern.iter().skip(2).position(|c| *c == b']') {
+                Some(end) => end + 2,
+                None => return !name.is_empty() && name[0] == b'[' && fnmatch(&pattern[1..], &name[1..]),
+            };
+            if name.is_empty() {
+                return false;
+            }
+            let (negate, set) = match pattern[1] {
+                b'!' | b'^' => (true, &pattern[2..end]),
+                _ => (false, &pattern[1..end]),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == b'-' {
+                    found |= set[i] <= name[0] && name[0] <= set[i + 2];
+                    i += 3;
+                } else {
+                    found |= set[i] == name[0];
+                    i += 1;
+                }
+            }
+            found != negate && fnmatch(&pattern[end + 1..], &name[1..])
+        }
+        Some(c) => !name.is_empty() && name[0] == *c && fnmatch(&pattern[1..], &name[1..]),
+    }
+}
+
+// `image/png` is looked up as itself, then as its family `image/*`.
+pub fn family_keys(mime: &str) -> Vec<String> {
+    let mut keys = vec![mime.to_string()];
+    if let Some(slash) = mime.find('/') {
+        keys.push(format!("{}/*", &mime[..slash]));
+    }
+    keys
+}
+
+#[cfg(test)]
+mod mime_db_tests {
+    use super::*;
+
+    fn db() -> MimeDb {
+        MimeDb::parse(include_str!("../tests/fixtures/globs2"))
+    }
+
+    #[test]
+    fn for_simple_suffixes() {
+        assert_eq!(Some("image/png"), db().lookup("logo.png"));
+        assert_eq!(Some("image/png"), db().lookup("LOGO.PNG"));
+        assert_eq!(Some("text/x-python"), db().lookup("setup.py"));
+    }
+
+    #[test]
+    fn for_literal_names() {
+        assert_eq!(Some("text/x-makefile"), db().lookup("Makefile"));
+        assert_eq!(Some("text/x-makefile"), db().lookup("makefile"));
+    }
+
+    #[test]
+    fn for_case_sensitive_rules() {
+        assert_eq!(Some("text/x-c++src"), db().lookup("main.C"));
+        assert_eq!(Some("text/x-csrc"), db().lookup("main.c"));
+    }
+
+    #[test]
+    fn for_longest_and_heaviest_match() {
+        assert_eq!(Some("application/x-compressed-tar"), db().lookup("release.tar.gz"));
+        assert_eq!(Some("application/gzip"), db().lookup("notes.gz"));
+        assert_eq!(Some("application/x-sharedlib"), db().lookup("libc.so.6"));
+    }
+
+    #[test]
+    fn when_lowercasing_changes_lengths() {
+        assert_eq!(Some("image/png"), db().lookup("İ.png"));
+        assert_eq!(Some("image/png"), db().lookup("ȺȺ.PNG"));
+        assert_eq!(None, db().lookup("ȺȺ.x"));
+    }
+
+    #[test]
+    fn when_nothing_matches() {
+        assert_eq!(None, db().lookup("configure"))
+    }
+
+    #[test]
+    fn for_family_keys() {
+        assert_eq!(vec!["image/png".to_string(), "image/*".to_string()], family_keys("image/png"))
+    }
+}
+
+#[cfg(test)]
+mod fnmatch_tests {
+    use super::*;
+
+    #[test]
+    fn for_wildcards() {
+        assert!(fnmatch(b"*.so.[0-9]*", b"libc.so.6"));
+        assert!(!fnmatch(b"*.so.[0-9]*", b"libc.so.x"));
+        assert!(fnmatch(b"?akefile", b"makefile"));
+        assert!(fnmatch(b"[!a]*", b"bcd"));
+        assert!(!fnmatch(b"[!a]*", b"abc"));
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Mutex;
+use std::thread;
+
+// Fixed number of workers for per-entry work (lstat, sniffing, ...), which
+// dominates on slow or network-mounted filesystems.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Pool {
+    pub threads: usize,
+}
+
+impl Default for Pool {
+    fn default() -> Pool {
+        Pool::new(thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+    }
+}
+
+impl Pool {
+    pub fn new(threads: usize) -> Pool {
+        Pool { threads: threads.max(1) }
+    }
+
+    // Like `items.into_iter().map(f).collect()`, results in input order.
+    pub fn map<T, U, F>(&self, items: Vec<T>, f: F) -> Vec<U>
+        where T: Send, U: Send, F: Fn(T) -> U + Sync {
+        let workers = self.threads.min(items.len());
+        if workers <= 1 {
+            return items.into_iter().map(f).collect();
+        }
+        let len = items.len();
+        let queue = Mutex::new(items.into_iter().enumerate());
+        let mut done: Vec<(usize, U)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers).map(|_| scope.spawn(|| {
+                let mut done = vec![];
+                loop {
+                    // NOTE: The lock guard must not outlive taking the next item.
+                    let next = queue.lock().unwrap().next();
+                    match next {
+                        Some((i, item)) => done.push((i, f(item))),
+                        None => return done,
+                    }
+                }
+            })).collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        });
+        debug_assert_eq!(len, done.len());
+        done.sort_unstable_by_key(|&(i, _)| i);
+        done.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+#[cfg(test)]
+mod pool_tests {
+    use super::*;
+
+    #[test]
+    fn for_order() {
+        let items: Vec<usize> = (0..1000).collect();
+        assert_eq!(items.iter().map(|i| i * 2).collect::<Vec<_>>(), Pool::new(8).map(items, |i| i * 2));
+    }
+
+    #[test]
+    fn when_single_threaded() {
+        assert_eq!(Pool::new(1), Pool::new(0));
+        assert_eq!(vec!["a".to_string()], Pool::new(1).map(vec!["a"], |s| s.to_string()));
+    }
+
+    #[test]
+    fn when_bounded() {
+        let ids = Pool::new(3).map((0..200).collect(), |_: usize| thread::current().id());
+        let mut distinct = ids.clone();
+        distinct.sort_by_key(|id| format!("{:?}", id));
+        distinct.dedup();
+        assert!(distinct.len() <= 3 && !ids.contains(&thread::current().id()));
+    }
+
+    #[test]
+    fn when_empty() {
+        assert_eq!(Vec::<u8>::new(), Pool::new(4).map(vec![], |i: u8| i));
+    }
+}
+
+#[cfg(test)]
+mod pool_benchmarks {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+    use self::super::super::formatter::get_attr;
+    use self::super::super::testing::{default_config, TempDir};
+
+    // cargo test --release -- --ignored --nocapture pool_benchmarks
+    #[test]
+    #[ignore]
+    fn for_100k_entries() {
+        let dir = TempDir::new("pool-bench");
+        for i in 0..100_000 {
+            fs::write(dir.join(&format!("file-{}.rs", i)), "fn main() {}").unwrap();
+        }
+        let config = default_config();
+        let paths: Vec<_> = fs::read_dir(&dir.0).unwrap().map(|e| e.unwrap().path()).collect();
+        let attrs = |pool: Pool| {
+            let start = Instant::now();
+            let attrs = pool.map(paths.clone(), |path| get_attr(&config, &path));
+            (attrs, start.elapsed())
+        };
+        let (serial, serial_time) = attrs(Pool::new(1));
+        // NOTE: Fixed rather than one per CPU, since the wait is on the filesystem.
+        let pool = Pool::new(8);
+        let (parallel, parallel_time) = attrs(pool);
+        println!("1 thread: {:?}, {} threads: {:?}", serial_time, pool.threads, parallel_time);
+        assert_eq!(100_000, parallel.len());
+        assert_eq!(serial, parallel);
+    }
+}
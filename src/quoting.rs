@@ -0,0 +1,224 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::str::FromStr;
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum QuotingStyle {
+    // As is; names that are not UTF-8 are decoded lossily.
+    Literal,
+    // Single quoted when the shell would need it.
+    Shell,
+    // Like shell, with control characters and bad bytes as `$'\n'`.
+    ShellEscape,
+    // Double quoted with C escapes.
+    C,
+    // C escapes without the quotes.
+    Escape,
+}
+
+impl FromStr for QuotingStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<QuotingStyle, String> {
+        match value {
+            "literal" => Ok(QuotingStyle::Literal),
+            "shell" => Ok(QuotingStyle::Shell),
+            "shell-escape" => Ok(QuotingStyle::ShellEscape),
+            "c" => Ok(QuotingStyle::C),
+            "escape" => Ok(QuotingStyle::Escape),
+            _ => Err(format!("Unknown QuotingStyle: {}", value)),
+        }
+    }
+}
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Quoting {
+    pub style: QuotingStyle,
+    // Print `?` for control characters in literal and shell styles, like `ls -q`.
+    pub hide_control: bool,
+}
+
+impl Default for Quoting {
+    fn default() -> Quoting {
+        Quoting { style: QuotingStyle::Literal, hide_control: false }
+    }
+}
+
+// A name split into printable text and runs that need escaping.
+enum Part<'a> {
+    Text(&'a str),
+    Raw(Vec<u8>),
+}
+
+fn parts(name: &OsStr) -> Vec<Part<'_>> {
+    let mut parts = vec![];
+    let mut raw = vec![];
+    for chunk in name.as_bytes().utf8_chunks() {
+        let text = chunk.valid();
+        let mut start = 0;
+        for (i, c) in text.char_indices() {
+            if c.is_control() {
+                if start < i {
+                    if !raw.is_empty() {
+                        parts.push(Part::Raw(raw));
+                        raw = vec![];
+                    }
+                    parts.push(Part::Text(&text[start..i]));
+                }
+                raw.extend_from_slice(&text.as_bytes()[i..i + c.len_utf8()]);
+                start = i + c.len_utf8();
+            }
+        }
+        if start < text.len() {
+            if !raw.is_empty() {
+                parts.push(Part::Raw(raw));
+                raw = vec![];
+            }
+            parts.push(Part::Text(&text[start..]));
+        }
+        raw.extend_from_slice(chunk.invalid());
+    }
+    if !raw.is_empty() {
+        parts.push(Part::Raw(raw));
+    }
+    parts
+}
+
+fn needs_shell_quotes(name: &str) -> bool {
+    name.is_empty()
+        || name.starts_with('~')
+        || name.chars().any(|c| !(c.is_alphanumeric() || "%+,-./:=@_^".contains(c)))
+}
+
+fn c_escape(c: u8, out: &mut String) {
+    match c {
+        b'\x07' => out.push_str("\\a"),
+        b'\x08' => out.push_str("\\b"),
+        b'\x0c' => out.push_str("\\f"),
+        b'\n' => out.push_str("\\n"),
+        b'\r' => out.push_str("\\r"),
+        b'\t' => out.push_str("\\t"),
+        b'\x0b' => out.push_str("\\v"),
+        _ => out.push_str(&format!("\\{:03o}", c)),
+    }
+}
+
+impl Quoting {
+    pub fn quote(&self, name: &OsStr) -> String {
+        match self.style {
+            QuotingStyle::Literal => self.literal(name),
+            QuotingStyle::Shell => {
+                let name = self.literal(name);
+                if needs_shell_quotes(&name) {
+                    format!("'{}'", name.replace('\'', "'\\''"))
+                } else {
+                    name
+                }
+            }
+            QuotingStyle::ShellEscape => shell_escape(name),
+            QuotingStyle::C => format!("\"{}\"", escape(name, true)),
+            QuotingStyle::Escape => escape(name, false),
+        }
+    }
+
+    fn literal(&self, name: &OsStr) -> String {
+        let name = name.to_string_lossy();
+        if self.hide_control {
+            name.chars().map(|c| if c.is_control() || c == '\u{fffd}' { '?' } else { c }).collect()
+        } else {
+            name.into_owned()
+        }
+    }
+}
+
+fn shell_escape(name: &OsStr) -> String {
+    let parts = parts(name);
+    match parts.as_slice() {
+        [] => "''".to_string(),
+        [Part::Text(text)] if !needs_shell_quotes(text) => text.to_string(),
+        [Part::Text(text)] => format!("'{}'", text.replace('\'', "'\\''")),
+        _ => {
+            let mut out = String::new();
+            for part in &parts {
+                match *part {
+                    Part::Text(text) => out.push_str(&format!("'{}'", text.replace('\'', "'\\''"))),
+                    Part::Raw(ref bytes) => {
+                        out.push_str("$'");
+                        for c in bytes {
+                            c_escape(*c, &mut out);
+                        }
+                        out.push('\'');
+                    }
+                }
+            }
+            out
+        }
+    }
+}
+
+fn escape(name: &OsStr, quoted: bool) -> String {
+    let mut out = String::new();
+    for part in parts(name) {
+        match part {
+            Part::Text(text) => for c in text.chars() {
+                match c {
+                    '\\' => out.push_str("\\\\"),
+                    '"' if quoted => out.push_str("\\\""),
+                    ' ' if !quoted => out.push_str("\\ "),
+                    c => out.push(c),
+                }
+            },
+            Part::Raw(bytes) => for c in bytes {
+                c_escape(c, &mut out);
+            },
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod quoting_tests {
+    use super::*;
+
+    fn quote(style: QuotingStyle, name: &[u8]) -> String {
+        Quoting { style, hide_control: false }.quote(OsStr::from_bytes(name))
+    }
+
+    #[test]
+    fn for_plain_names() {
+        for style in &[QuotingStyle::Literal, QuotingStyle::Shell, QuotingStyle::ShellEscape, QuotingStyle::Escape] {
+            assert_eq!("main.rs", quote(*style, b"main.rs"));
+        }
+        assert_eq!("\"main.rs\"", quote(QuotingStyle::C, b"main.rs"));
+        assert_eq!("zażółć", quote(QuotingStyle::ShellEscape, "zażółć".as_bytes()));
+    }
+
+    #[test]
+    fn for_shell_styles() {
+        assert_eq!("'my file'", quote(QuotingStyle::Shell, b"my file"));
+        assert_eq!("'it'\\''s'", quote(QuotingStyle::Shell, b"it's"));
+        assert_eq!("'a'$'\\n''b'", quote(QuotingStyle::ShellEscape, b"a\nb"));
+        assert_eq!("'bad'$'\\377''.rs'", quote(QuotingStyle::ShellEscape, b"bad\xff.rs"));
+        assert_eq!("$'\\033''[31m'", quote(QuotingStyle::ShellEscape, b"\x1b[31m"));
+    }
+
+    #[test]
+    fn for_c_styles() {
+        assert_eq!("\"a\\tb\\\"c\\377\"", quote(QuotingStyle::C, b"a\tb\"c\xff"));
+        assert_eq!("my\\ file\\n", quote(QuotingStyle::Escape, b"my file\n"));
+        assert_eq!("back\\\\slash", quote(QuotingStyle::Escape, b"back\\slash"));
+    }
+
+    #[test]
+    fn when_hiding_control_characters() {
+        let quoting = Quoting { style: QuotingStyle::Literal, hide_control: true };
+        assert_eq!("a?b?", quoting.quote(OsStr::from_bytes(b"a\nb\xff")));
+        assert_eq!("a\nb\u{fffd}", quote(QuotingStyle::Literal, b"a\nb\xff"));
+    }
+
+    #[test]
+    fn when_parsing_styles() {
+        assert_eq!(Ok(QuotingStyle::ShellEscape), "shell-escape".parse());
+        assert!("locale".parse::<QuotingStyle>().is_err());
+    }
+}
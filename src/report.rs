@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde_json;
+use termion::color;
+
+use self::super::colors::ColorType;
+use self::super::formatter::{color_for, Entry, EntryConfig, Kind, SPECIAL};
+use self::super::size::human_size;
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ReportMode {
+    // After the listing.
+    Footer,
+    // Instead of the listing.
+    Only,
+    Json,
+}
+
+impl FromStr for ReportMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<ReportMode, String> {
+        match value {
+            "footer" => Ok(ReportMode::Footer),
+            "only" => Ok(ReportMode::Only),
+            "json" => Ok(ReportMode::Json),
+            _ => Err(format!("Unknown ReportMode: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct Report {
+    pub folders: usize,
+    pub files: usize,
+    pub symlinks: usize,
+    pub hidden: usize,
+    pub recognized: usize,
+    pub unrecognized: usize,
+    pub total_bytes: u64,
+    pub by_key: BTreeMap<String, usize>,
+}
+
+impl Report {
+    pub fn new(entries: &[Entry]) -> Report {
+        let mut report = Report::default();
+        for entry in entries {
+            if entry.kind().is_dir() {
+                report.folders += 1;
+            } else if entry.kind() == Kind::Symlink {
+                report.symlinks += 1;
+            } else {
+                report.files += 1;
+                if entry.is_recognized() {
+                    report.recognized += 1;
+                } else {
+                    report.unrecognized += 1;
+                }
+                report.total_bytes += entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+            }
+            if entry.is_hidden() {
+                report.hidden += 1;
+            }
+            // NOTE: Reported by type, so `@symlink` is just `symlink`.
+            *report.by_key.entry(entry.key().trim_start_matches(SPECIAL).to_string()).or_insert(0) += 1;
+        }
+        report
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let by_key: Vec<String> = self.by_key.iter().map(|(key, count)| format!("{} {}", key, count)).collect();
+        vec![
+            format!("Found {} entries, {} in total.", self.folders + self.files + self.symlinks, human_size(self.total_bytes)),
+            format!("  Folders            : {}", self.folders),
+            format!("  Files              : {}", self.files),
+            format!("  Symlinks           : {}", self.symlinks),
+            format!("  Hidden             : {}", self.hidden),
+            format!("  Recognized files   : {}", self.recognized),
+            format!("  Unrecognized files : {}", self.unrecognized),
+            format!("  By type            : {}", by_key.join(", ")),
+        ]
+    }
+
+    pub fn render(&self, config: &EntryConfig) -> Vec<String> {
+        self.lines().iter().map(|line| {
+            format!(
+                "{color}{line}{reset}",
+                color = color::Fg(color_for(config, &ColorType::Report)),
+                line = line,
+                reset = color::Fg(color::Reset),
+            )
+        }).collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod report_tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use self::super::super::testing::{default_config, TempDir};
+
+    fn report() -> Report {
+        let dir = TempDir::new("report");
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("lib.rs"), "pub mod a;").unwrap();
+        fs::write(dir.join(".netrc"), "machine").unwrap();
+        fs::create_dir_all(dir.join("src/bin")).unwrap();
+        symlink(dir.join("main.rs"), dir.join("link")).unwrap();
+        let config = default_config();
+        let entries: Vec<Entry> = ["main.rs", "lib.rs", ".netrc", "src", "link"].iter().map(|name| {
+            Entry::new(&config, dir.join(name), None)
+        }).collect();
+        Report::new(&entries)
+    }
+
+    #[test]
+    fn for_counts() {
+        let report = report();
+        assert_eq!((1, 3, 1, 1), (report.folders, report.files, report.symlinks, report.hidden));
+        assert_eq!((2, 1), (report.recognized, report.unrecognized));
+        assert_eq!(12 + 10 + 7, report.total_bytes);
+        assert_eq!(Some(&2), report.by_key.get("rs"));
+        assert_eq!(Some(&1), report.by_key.get("folder"));
+    }
+
+    #[test]
+    fn for_text_lines() {
+        let lines = report().lines();
+        assert_eq!("Found 5 entries, 29 B in total.", lines[0]);
+        assert_eq!("  By type            : folder 1, hidden 1, rs 2, symlink 1", lines[7]);
+    }
+
+    #[test]
+    fn for_json() {
+        let json = report().to_json();
+        assert!(json.contains("\"total_bytes\": 29"));
+        assert!(json.contains("\"rs\": 2"));
+    }
+
+    #[test]
+    fn when_parsing_modes() {
+        assert_eq!(Ok(ReportMode::Json), "json".parse());
+        assert!("xml".parse::<ReportMode>().is_err());
+    }
+}
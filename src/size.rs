@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use self::super::pool::Pool;
+
+const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+// `1536` => `1.5 KiB`; bytes are shown without a fraction.
+pub fn human_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SizeMode {
+    // Sum of file lengths, like `du --apparent-size`.
+    Apparent,
+    // Allocated blocks, like plain `du`.
+    Disk,
+}
+
+impl FromStr for SizeMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<SizeMode, String> {
+        match value {
+            "apparent" => Ok(SizeMode::Apparent),
+            "disk" => Ok(SizeMode::Disk),
+            _ => Err(format!("Unknown SizeMode: {}", value)),
+        }
+    }
+}
+
+// (dev, inode) pairs a total has counted.
+type Seen = Mutex<HashSet<(u64, u64)>>;
+
+// Cumulative directory sizes. Clones share the per-directory cache, so one
+// walk serves every view of the same listing.
+#[derive(Debug, Clone)]
+pub struct DiskUsage {
+    pub mode: SizeMode,
+    pub one_file_system: bool,
+    // Walks the top-level subdirectories of each total.
+    pub pool: Pool,
+    cache: Arc<Mutex<HashMap<path::PathBuf, u64>>>,
+}
+
+impl DiskUsage {
+    pub fn new(mode: SizeMode, one_file_system: bool, pool: Pool) -> DiskUsage {
+        DiskUsage {
+            mode,
+            one_file_system,
+            pool,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn size_of(&self, meta: &fs::Metadata) -> u64 {
+        match self.mode {
+            SizeMode::Apparent => meta.len(),
+            SizeMode::Disk => meta.blocks() * 512,
+        }
+    }
+
+    // Hard links (and anything reachable twice) only count the first time
+    // within a total; `seen` is never shared between totals.
+    fn first_visit(seen: &Seen, meta: &fs::Metadata) -> bool {
+        (meta.nlink() <= 1 && !meta.is_dir()) || seen.lock().unwrap().insert((meta.dev(), meta.ino()))
+    }
+
+    pub fn total(&self, path: &path::Path) -> u64 {
+        if let Some(size) = self.cache.lock().unwrap().get(path) {
+            return *size;
+        }
+        let meta = match fs::symlink_metadata(path) {
+            Ok(meta) => meta,
+            Err(_) => return 0,
+        };
+        if !meta.is_dir() {
+            return self.size_of(&meta);
+        }
+        let seen = Mutex::new(HashSet::new());
+        let mut size = if DiskUsage::first_visit(&seen, &meta) { self.size_of(&meta) } else { 0 };
+        let dev = meta.dev();
+        let mut subdirs = vec![];
+        for (child, child_meta) in self.children(path, dev) {
+            if child_meta.is_dir() {
+                subdirs.push((child, child_meta));
+            } else if DiskUsage::first_visit(&seen, &child_meta) {
+                size += self.size_of(&child_meta);
+            }
+        }
+        // NOTE: Only the top level fans out, over the pool's workers.
+        size += self.pool.map(subdirs, |(dir, meta)| self.walk(&seen, dir, meta, dev)).into_iter().sum::<u64>();
+        self.cache.lock().unwrap().insert(path.to_path_buf(), size);
+        size
+    }
+
+    fn children(&self, path: &path::Path, dev: u64) -> Vec<(path::PathBuf, fs::Metadata)> {
+        let dir = match fs::read_dir(path) {
+            Ok(dir) => dir,
+            Err(_) => return vec![],
+        };
+        dir.filter_map(|entry| {
+            let entry = entry.ok()?;
+            let meta = entry.metadata().ok()?;
+            if self.one_file_system && meta.dev() != dev {
+                None
+            } else {
+                Some((entry.path(), meta))
+            }
+        }).collect()
+    }
+
+    // Depth first with a stack of its own, so deep trees cannot overflow ours.
+    fn walk(&self, seen: &Seen, path: path::PathBuf, meta: fs::Metadata, dev: u64) -> u64 {
+        let mut size = 0;
+        let mut stack = vec![(path, meta)];
+        while let Some((dir, meta)) = stack.pop() {
+            if !DiskUsage::first_visit(seen, &meta) {
+                continue;
+            }
+            size += self.size_of(&meta);
+            for (child, child_meta) in self.children(&dir, dev) {
+                if child_meta.is_dir() {
+                    stack.push((child, child_meta));
+                } else if DiskUsage::first_visit(seen, &child_meta) {
+                    size += self.size_of(&child_meta);
+                }
+            }
+        }
+        size
+    }
+}
+
+#[cfg(test)]
+mod human_size_tests {
+    use super::*;
+
+    #[test]
+    fn for_simple_cases() {
+        assert_eq!("0 B", human_size(0));
+        assert_eq!("1023 B", human_size(1023));
+        assert_eq!("1.5 KiB", human_size(1536));
+        assert_eq!("2.0 GiB", human_size(2 * 1024 * 1024 * 1024));
+    }
+}
+
+#[cfg(test)]
+mod disk_usage_tests {
+    use super::*;
+    use self::super::super::testing::TempDir;
+
+    fn fixture() -> TempDir {
+        let dir = TempDir::new("disk-usage");
+        fs::create_dir_all(dir.join("a/b/c")).unwrap();
+        fs::create_dir_all(dir.join("d")).unwrap();
+        fs::write(dir.join("a/one"), vec![0u8; 100]).unwrap();
+        fs::write(dir.join("a/b/c/two"), vec![0u8; 200]).unwrap();
+        fs::write(dir.join("d/three"), vec![0u8; 300]).unwrap();
+        dir
+    }
+
+    fn dir_size(path: &path::Path) -> u64 {
+        fs::symlink_metadata(path).unwrap().len()
+    }
+
+    #[test]
+    fn for_apparent_size() {
+        let dir = fixture();
+        let du = DiskUsage::new(SizeMode::Apparent, false, Pool::new(2));
+        let dirs = ["", "a", "a/b", "a/b/c", "d"].iter().map(|d| dir_size(&dir.join(d))).sum::<u64>();
+        assert_eq!(600 + dirs, du.total(&dir.0));
+        assert_eq!(300, du.total(&dir.join("d/three")));
+    }
+
+    #[test]
+    fn for_hard_links() {
+        let dir = fixture();
+        fs::hard_link(dir.join("a/one"), dir.join("a/b/again")).unwrap();
+        let du = DiskUsage::new(SizeMode::Apparent, false, Pool::new(2));
+        let dirs = ["a", "a/b", "a/b/c"].iter().map(|d| dir_size(&dir.join(d))).sum::<u64>();
+        assert_eq!(300 + dirs, du.total(&dir.join("a")));
+    }
+
+    #[test]
+    fn when_cached() {
+        let dir = fixture();
+        let du = DiskUsage::new(SizeMode::Disk, false, Pool::new(2));
+        let size = du.total(&dir.join("a"));
+        assert!(size > 0);
+        assert_eq!(size, du.clone().total(&dir.join("a")));
+    }
+
+    #[test]
+    fn for_wide_and_deep_trees() {
+        let dir = TempDir::new("disk-usage-wide");
+        for i in 0..500 {
+            fs::create_dir(dir.join(&format!("{}", i))).unwrap();
+            fs::write(dir.join(&format!("{}/file", i)), vec![0u8; 10]).unwrap();
+        }
+        let deep: path::PathBuf = (0..1000).map(|_| "d").collect();
+        fs::create_dir_all(dir.0.join(&deep)).unwrap();
+        fs::write(dir.0.join(&deep).join("file"), vec![0u8; 7]).unwrap();
+        let du = DiskUsage::new(SizeMode::Apparent, false, Pool::new(2));
+        let mut dirs = dir_size(&dir.0);
+        dirs += (0..500).map(|i| dir_size(&dir.join(&format!("{}", i)))).sum::<u64>();
+        dirs += deep.ancestors().filter(|d| !d.as_os_str().is_empty()).map(|d| dir_size(&dir.0.join(d))).sum::<u64>();
+        assert_eq!(500 * 10 + 7 + dirs, du.total(&dir.0));
+    }
+
+    #[test]
+    fn when_totalling_a_child_first() {
+        let dir = fixture();
+        fs::hard_link(dir.join("a/one"), dir.join("a/b/again")).unwrap();
+        let du = DiskUsage::new(SizeMode::Apparent, false, Pool::new(2));
+        let child = du.total(&dir.join("a/b"));
+        let dirs = ["a/b", "a/b/c"].iter().map(|d| dir_size(&dir.join(d))).sum::<u64>();
+        assert_eq!(300 + dirs, child);
+        let fresh = DiskUsage::new(SizeMode::Apparent, false, Pool::new(2)).total(&dir.0);
+        assert_eq!(fresh, du.total(&dir.0));
+        assert_eq!(child + dir_size(&dir.join("a")), du.total(&dir.join("a")));
+    }
+
+    #[test]
+    fn when_parsing_modes() {
+        assert_eq!(Ok(SizeMode::Disk), "disk".parse());
+        assert!("blocks".parse::<SizeMode>().is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::io::Read;
+use std::path;
+use std::str;
+
+const SNIFF_LEN: usize = 512;
+
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x7fELF", "elf"),
+    (b"\x89PNG\r\n\x1a\n", "png"),
+    (b"PK\x03\x04", "zip"),
+    (b"%PDF-", "pdf"),
+    (b"\x1f\x8b", "gz"),
+];
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Sniffed {
+    Interpreter(String),
+    Magic(&'static str),
+    Text,
+    Binary,
+}
+
+impl Sniffed {
+    // Key fed into the same icon/alias lookup as extensions.
+    pub fn key(&self) -> String {
+        match *self {
+            Sniffed::Interpreter(ref name) => name.clone(),
+            Sniffed::Magic(key) => key.to_string(),
+            Sniffed::Text => "txt".to_string(),
+            Sniffed::Binary => "binary".to_string(),
+        }
+    }
+
+    // Generic results should not hide more useful facts, like the exec bit.
+    pub fn is_generic(&self) -> bool {
+        matches!(*self, Sniffed::Text | Sniffed::Binary | Sniffed::Magic("elf"))
+    }
+}
+
+pub fn sniff(path: &path::Path) -> Option<Sniffed> {
+    let mut buf = Vec::with_capacity(SNIFF_LEN);
+    let file = fs::File::open(path).ok()?;
+    file.take(SNIFF_LEN as u64).read_to_end(&mut buf).ok()?;
+    sniff_bytes(&buf)
+}
+
+fn sniff_bytes(bytes: &[u8]) -> Option<Sniffed> {
+    if bytes.is_empty() {
+        return None;
+    }
+    if bytes.starts_with(b"#!") {
+        if let Some(name) = interpreter(bytes) {
+            return Some(Sniffed::Interpreter(name));
+        }
+    }
+    for &(magic, key) in MAGIC {
+        if bytes.starts_with(magic) {
+            return Some(Sniffed::Magic(key));
+        }
+    }
+    if is_text(bytes) {
+        Some(Sniffed::Text)
+    } else {
+        Some(Sniffed::Binary)
+    }
+}
+
+// `#!/usr/bin/env -S python3.11 -u` => `python`
+fn interpreter(bytes: &[u8]) -> Option<String> {
+    let line = bytes[2..].split(|b| *b == b'\n').next()?;
+    let line = str::from_utf8(line).ok()?;
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+    let name = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+fn is_text(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return false;
+    }
+    match str::from_utf8(bytes) {
+        Ok(_) => true,
+        // NOTE: The read may have cut a multi-byte character in half.
+        Err(e) => e.error_len().is_none() && bytes.len() - e.valid_up_to() < 4,
+    }
+}
+
+#[cfg(test)]
+mod sniff_tests {
+    use super::*;
+
+    #[test]
+    fn for_shebangs() {
+        assert_eq!(Some(Sniffed::Interpreter("python".to_string())), sniff_bytes(b"#!/usr/bin/env python3\nprint(1)"));
+        assert_eq!(Some(Sniffed::Interpreter("bash".to_string())), sniff_bytes(b"#!/bin/bash -e\n"));
+        assert_eq!(Some(Sniffed::Interpreter("node".to_string())), sniff_bytes(b"#!/usr/bin/env -S NODE_ENV=dev node\n"));
+    }
+
+    #[test]
+    fn for_magic_numbers() {
+        assert_eq!(Some(Sniffed::Magic("elf")), sniff_bytes(b"\x7fELF\x02\x01\x01\0\0"));
+        assert_eq!(Some(Sniffed::Magic("png")), sniff_bytes(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert_eq!(Some(Sniffed::Magic("pdf")), sniff_bytes(b"%PDF-1.4"));
+        assert_eq!(Some(Sniffed::Magic("gz")), sniff_bytes(b"\x1f\x8b\x08\0"));
+    }
+
+    #[test]
+    fn for_text_and_binary() {
+        assert_eq!(Some(Sniffed::Text), sniff_bytes("zażółć\n".as_bytes()));
+        assert_eq!(Some(Sniffed::Text), sniff_bytes(&"zażółć".as_bytes()[..3]));
+        assert_eq!(Some(Sniffed::Binary), sniff_bytes(b"\x00\x01\x02"));
+        assert_eq!(Some(Sniffed::Binary), sniff_bytes(b"\xff\xfe\xfd\xfc\xfb"));
+    }
+
+    #[test]
+    fn when_file_is_empty() {
+        assert_eq!(None, sniff_bytes(b""))
+    }
+}
@@ -0,0 +1,222 @@
+use std::cmp::max;
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::str::FromStr;
+
+use self::super::cell::Segment;
+use self::super::colors::ColorType;
+use self::super::formatter::{Entry, EntryConfig};
+use self::super::tabulator::{Config, OneLineTabulator, Tabulator};
+use self::super::timestamp::TimeField;
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Column {
+    Perms,
+    Size,
+    User,
+    Group,
+    // The --time field, or a fixed one.
+    Time(Option<TimeField>),
+    Mime,
+    Icon,
+    Name,
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Column, String> {
+        match value {
+            "perms" | "permissions" => Ok(Column::Perms),
+            "size" => Ok(Column::Size),
+            "user" => Ok(Column::User),
+            "group" => Ok(Column::Group),
+            "time" => Ok(Column::Time(None)),
+            "mtime" => Ok(Column::Time(Some(TimeField::Modified))),
+            "atime" => Ok(Column::Time(Some(TimeField::Accessed))),
+            "ctime" => Ok(Column::Time(Some(TimeField::Changed))),
+            "btime" => Ok(Column::Time(Some(TimeField::Created))),
+            "mime" => Ok(Column::Mime),
+            "icon" => Ok(Column::Icon),
+            "name" => Ok(Column::Name),
+            // NOTE: Nothing collects git status yet.
+            "git" => Err("Column git is not supported".to_string()),
+            _ => Err(format!("Unknown Column: {}", value)),
+        }
+    }
+}
+
+impl Column {
+    pub fn align(&self) -> Align {
+        match *self {
+            Column::Size => Align::Right,
+            _ => Align::Left,
+        }
+    }
+
+    // What -l shows without --columns.
+    pub fn defaults(size: bool, mime: bool) -> Vec<Column> {
+        let mut columns = vec![Column::User, Column::Group];
+        if size {
+            columns.push(Column::Size);
+        }
+        columns.push(Column::Time(None));
+        if mime {
+            columns.push(Column::Mime);
+        }
+        columns.extend_from_slice(&[Column::Icon, Column::Name]);
+        columns
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TableCell {
+    pub segment: Segment,
+    pub align: Align,
+}
+
+impl TableCell {
+    pub fn new(text: String, color: Option<ColorType>, align: Align) -> TableCell {
+        TableCell { segment: Segment::new(text, color), align }
+    }
+
+    pub fn width(&self) -> usize {
+        self.segment.width()
+    }
+
+    pub fn render(&self, config: &EntryConfig, width: usize) -> String {
+        let pad = width.saturating_sub(self.width());
+        match self.align {
+            Align::Left => self.segment.render(config, pad),
+            Align::Right => " ".repeat(pad) + &self.segment.render(config, 0),
+        }
+    }
+}
+
+// `drwxr-sr-x`, as in `ls -l`.
+pub fn perms(meta: &fs::Metadata) -> String {
+    let file_type = meta.file_type();
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_block_device() {
+        'b'
+    } else if file_type.is_char_device() {
+        'c'
+    } else {
+        '-'
+    };
+    let mode = meta.permissions().mode();
+    let bit = |mask: u32, c: char| if mode & mask != 0 { c } else { '-' };
+    let special = |exec: u32, special: u32, on: char| match (mode & exec != 0, mode & special != 0) {
+        (true, true) => on,
+        (false, true) => on.to_ascii_uppercase(),
+        (true, false) => 'x',
+        (false, false) => '-',
+    };
+    [
+        kind,
+        bit(0o400, 'r'), bit(0o200, 'w'), special(0o100, 0o4000, 's'),
+        bit(0o040, 'r'), bit(0o020, 'w'), special(0o010, 0o2000, 's'),
+        bit(0o004, 'r'), bit(0o002, 'w'), special(0o001, 0o1000, 't'),
+    ].iter().collect()
+}
+
+// Aligns formatter rows as a table: every column as wide as its widest cell.
+#[derive(Debug)]
+pub struct TableTabulator;
+impl Tabulator for TableTabulator {
+    fn tabulate(&self, config: &Config, names: Vec<Entry>) -> Vec<Vec<String>> {
+        let rows: Option<Vec<Vec<TableCell>>> = names.iter().map(|entry| config.formatter.row(&config.entry, entry)).collect();
+        let rows = match rows {
+            Some(rows) => rows,
+            None => return OneLineTabulator.tabulate(config, names),
+        };
+        let mut widths: Vec<usize> = vec![];
+        for row in &rows {
+            widths.resize(max(widths.len(), row.len()), 0);
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = max(widths[i], cell.width());
+            }
+        }
+        let last = widths.iter().rposition(|width| *width > 0).unwrap_or(0);
+        rows.iter().map(|row| {
+            let cells: Vec<String> = row.iter().enumerate().filter(|&(i, _)| widths[i] > 0).map(|(i, cell)| {
+                // NOTE: The last column is not padded, so lines do not end in spaces.
+                let width = if i == last && cell.align == Align::Left { 0 } else { widths[i] };
+                cell.render(&config.entry, width)
+            }).collect();
+            vec![cells.join(" ")]
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod table_tests {
+    use super::*;
+    use self::super::super::formatter::LongFormat;
+    use self::super::super::testing::{default_config, TempDir};
+    use self::super::super::timestamp::{TimeFormat, TimeStyle};
+    use self::super::super::users::UserDb;
+    use self::super::super::tabulator::EntryOrder;
+
+    fn plain(rows: Vec<Vec<String>>) -> Vec<String> {
+        rows.iter().map(|row| {
+            let line: String = row.concat();
+            line.split('\x1b').enumerate().map(|(i, part)| if i == 0 { part } else { &part[part.find('m').unwrap() + 1..] }).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn for_perms() {
+        let dir = TempDir::new("table-perms");
+        fs::write(dir.join("run"), "exit 0").unwrap();
+        fs::set_permissions(dir.join("run"), fs::Permissions::from_mode(0o4754)).unwrap();
+        fs::create_dir(dir.join("tmp")).unwrap();
+        fs::set_permissions(dir.join("tmp"), fs::Permissions::from_mode(0o1776)).unwrap();
+        assert_eq!("-rwsr-xr--", perms(&fs::symlink_metadata(dir.join("run")).unwrap()));
+        assert_eq!("drwxrwxrwT", perms(&fs::symlink_metadata(dir.join("tmp")).unwrap()));
+    }
+
+    #[test]
+    fn for_columns() {
+        assert_eq!(Ok(Column::Time(Some(TimeField::Accessed))), "atime".parse());
+        assert_eq!(Align::Right, Column::Size.align());
+        assert!("git".parse::<Column>().is_err());
+        assert_eq!(vec![Column::User, Column::Group, Column::Time(None), Column::Icon, Column::Name], Column::defaults(false, false));
+    }
+
+    #[test]
+    fn for_aligned_columns() {
+        let dir = TempDir::new("table-columns");
+        fs::write(dir.join("a.rs"), "x").unwrap();
+        fs::write(dir.join("bb.rs"), "x".repeat(2000)).unwrap();
+        let config = Config {
+            entry: EntryConfig { icon_width: 0, ..default_config() },
+            max_width: 80,
+            formatter: Box::new(LongFormat {
+                time: TimeFormat::new(TimeStyle::Relative, TimeField::Modified),
+                columns: vec![Column::Name, Column::Icon, Column::Size],
+                users: UserDb::system(),
+                numeric: false,
+                sizes: None,
+            }),
+            entry_order: EntryOrder::Vertical,
+        };
+        let names = ["a.rs", "bb.rs"].iter().map(|name| Entry::new(&config.entry, dir.join(name), None)).collect();
+        let a = dir.join("a.rs").display().to_string();
+        let bb = dir.join("bb.rs").display().to_string();
+        assert_eq!(vec![format!("{}      1 B", a), format!("{} 2.0 KiB", bb)], plain(TableTabulator.tabulate(&config, names)));
+    }
+}
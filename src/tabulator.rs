@@ -2,14 +2,16 @@ use std::cmp::max;
 use num_iter::range_step;
 
 use std::fmt;
+use std::str::FromStr;
 
 use self::super::formatter::{Formatter,Entry,EntryConfig};
+use self::super::info::JsonTabulator;
 
 #[derive(Debug)]
 pub struct Config {
     pub entry: EntryConfig,
     pub max_width: usize,
-    pub formatter: Box<Formatter>,
+    pub formatter: Box<dyn Formatter>,
     pub entry_order : EntryOrder,
 }
 
@@ -21,8 +23,8 @@ pub enum EntryOrder {
     Vertical
 }
 
-fn break_lines_with<T : Clone>(order : EntryOrder, names : &Vec<T>, n_cols : usize) -> Rows<T> {
-    let n_rows = (names.len() + n_cols - 1) / n_cols;
+fn break_lines_with<T : Clone>(order : EntryOrder, names : &[T], n_cols : usize) -> Rows<T> {
+    let n_rows = names.len().div_ceil(n_cols);
     let mut rows = vec![Vec::with_capacity(n_cols); n_rows];
     for (i, out) in names.iter().enumerate() {
         let pos = match order {
@@ -39,12 +41,12 @@ mod horizontal_line_breaker_tests {
     use super::*;
     #[test]
     fn for_simple_case() {
-        assert_eq!(vec![vec![1,2], vec![3,4]], break_lines_with(EntryOrder::Horizontal, &vec![1,2,3,4], 2))
+        assert_eq!(vec![vec![1,2], vec![3,4]], break_lines_with(EntryOrder::Horizontal, &[1,2,3,4], 2))
     }
 
     #[test]
     fn when_only_one_row() {
-        assert_eq!(vec![vec![1,2,3]], break_lines_with(EntryOrder::Horizontal, &vec![1,2,3], 10))
+        assert_eq!(vec![vec![1,2,3]], break_lines_with(EntryOrder::Horizontal, &[1,2,3], 10))
     }
 }
 
@@ -53,18 +55,18 @@ mod vertical_line_breaker_tests {
     use super::*;
     #[test]
     fn for_simple_case() {
-        assert_eq!(vec![vec![1,3], vec![2,4]], break_lines_with(EntryOrder::Vertical, &vec![1,2,3,4], 2))
+        assert_eq!(vec![vec![1,3], vec![2,4]], break_lines_with(EntryOrder::Vertical, &[1,2,3,4], 2))
     }
 
     #[test]
     fn when_only_one_row() {
-        assert_eq!(vec![vec![1,2,3]], break_lines_with(EntryOrder::Vertical, &vec![1,2,3], 10))
+        assert_eq!(vec![vec![1,2,3]], break_lines_with(EntryOrder::Vertical, &[1,2,3], 10))
     }
 }
 
 type Output = Rows<String>;
 pub trait Tabulator: fmt::Debug {
-    fn tabulate(&self, &Config, Vec<Entry>) -> Output;
+    fn tabulate(&self, config: &Config, names: Vec<Entry>) -> Output;
 }
 
 type ColumnSetup = Vec<usize>;
@@ -72,7 +74,7 @@ type ColumnSetup = Vec<usize>;
 fn is_valid(out : &ColumnSetup, max_width : usize) -> bool {
     let mut width = 0;
     for c in out { width += *c }
-    return width < max_width
+    width < max_width
 }
 
 // NOTE: Assumes out has same-sized rows
@@ -94,32 +96,36 @@ mod is_valid_tests {
     use super::*;
     #[test]
     fn for_simple_case() {
-        assert_eq!(false, is_valid(&column_setup(vec![vec![1,2], vec![2,1]]), 2))
+        assert!(!is_valid(&column_setup(vec![vec![1,2], vec![2,1]]), 2))
     }
 
     #[test]
     fn when_total_col_width_exceeds_max() {
-        assert_eq!(false, is_valid(&column_setup(vec![vec![1,3], vec![3,1]]), 5))
+        assert!(!is_valid(&column_setup(vec![vec![1,3], vec![3,1]]), 5))
     }
 
     #[test]
     fn when_fits() {
-        assert_eq!(true, is_valid(&column_setup(vec![vec![1,2], vec![1,1]]), 4))
+        assert!(is_valid(&column_setup(vec![vec![1,2], vec![1,1]]), 4))
     }
 }
 
-fn predict_column_setup(config: &Config, names : &Vec<Entry>, n_cols : usize) -> ColumnSetup {
-    column_setup(break_lines_with(config.entry_order, &names.iter().map(|e| config.formatter.predict(e)).collect(), n_cols))
+fn predict_column_setup(config: &Config, names : &[Entry], n_cols : usize) -> ColumnSetup {
+    column_setup(break_lines_with(config.entry_order, &names.iter().map(|e| config.formatter.predict(&config.entry, e)).collect::<Vec<_>>(), n_cols))
 }
 
-fn is_valid_as_rows(config: &Config, names : &Vec<Entry>, n_cols : usize) -> Option<ColumnSetup> {
+fn is_valid_as_rows(config: &Config, names : &[Entry], n_cols : usize) -> Option<ColumnSetup> {
     let setup = predict_column_setup(config, names, n_cols);
     if is_valid(&setup, config.max_width) {
         Some(setup)
     } else { None }
 }
 
-fn format_as_rows(config : &Config, names : &Vec<Entry>, col_widths : ColumnSetup) -> Output {
+fn format_as_rows(config : &Config, names : &[Entry], col_widths : ColumnSetup) -> Output {
+    // NOTE: Nothing to list has no columns either.
+    if names.is_empty() {
+        return vec![]
+    }
     let n_cols = col_widths.len();
     let rows = break_lines_with(config.entry_order, names, n_cols);
     let entry_configs : Vec<EntryConfig> = col_widths.iter().map(|width| EntryConfig{width: *width, ..config.entry.clone()}).collect();
@@ -132,10 +138,10 @@ fn format_as_rows(config : &Config, names : &Vec<Entry>, col_widths : ColumnSetu
     out
 }
 
-fn max_width(config : &Config, names : &Vec<Entry>) -> usize {
+fn max_width(config : &Config, names : &[Entry]) -> usize {
     let mut width = 0;
     for l in names {
-        let cwidth = config.formatter.predict(l);
+        let cwidth = config.formatter.predict(&config.entry, l);
         if cwidth > width {
             width = cwidth;
         }
@@ -161,9 +167,9 @@ impl Tabulator for PlanningTabulator {
     }
 }
 
-fn binsearch<T, F>(range : &Vec<T>, pred : F) -> Option<usize>
+fn binsearch<T, F>(range : &[T], pred : F) -> Option<usize>
     where F: Fn(&T) -> bool {
-    let mut slice = &range[..];
+    let mut slice = range;
     let mut index = 0;
     let mut counter = range.len();
     loop {
@@ -196,23 +202,23 @@ mod binsearch_tests {
     use super::*;
     #[test]
     fn for_simple_case() {
-        assert_eq!(Some(1), binsearch(&vec![0,1,2], |i| *i >= 1))
+        assert_eq!(Some(1), binsearch(&[0,1,2], |i| *i >= 1))
     }
 
     #[test]
     fn when_nothing_matches() {
-        assert_eq!(None, binsearch(&vec![0,1,2], |i| *i >= 3))
+        assert_eq!(None, binsearch(&[0,1,2], |i| *i >= 3))
     }
 
     #[test]
     fn when_array_is_small() {
-        assert_eq!(Some(1), binsearch(&vec![0,1], |i| *i >= 1));
-        assert_eq!(Some(0), binsearch(&vec![0,1], |i| *i >= 0))
+        assert_eq!(Some(1), binsearch(&[0,1], |i| *i >= 1));
+        assert_eq!(Some(0), binsearch(&[0,1], |i| *i >= 0))
     }
 
     #[test]
     fn when_array_is_big() {
-        assert_eq!(Some(11), binsearch(&vec![0,1,2,3,4,5,6,7,8,9,10,11,12,13,14], |i| *i >= 11));
+        assert_eq!(Some(11), binsearch(&[0,1,2,3,4,5,6,7,8,9,10,11,12,13,14], |i| *i >= 11));
     }
 
     #[test]
@@ -249,3 +255,187 @@ impl Tabulator for NaiveTabulator {
         format_as_rows(config, &names, predict_column_setup(config, &names, rows as usize))
     }
 }
+
+// Entries as wide as their own text, without the column gap.
+fn format_unpadded(config : &Config, entry : &Entry) -> (String, usize) {
+    let width = config.formatter.predict(&config.entry, entry).saturating_sub(1);
+    (config.formatter.format(&EntryConfig{width, ..config.entry.clone()}, entry), width)
+}
+
+#[derive(Debug)]
+pub struct OneLineTabulator;
+impl Tabulator for OneLineTabulator {
+    fn tabulate(&self, config : &Config, names : Vec<Entry>) -> Output {
+        names.iter().map(|entry| vec![format_unpadded(config, entry).0]).collect()
+    }
+}
+
+// `a, b, c` wrapped to the width, like `ls -m`.
+#[derive(Debug)]
+pub struct CommasTabulator;
+impl Tabulator for CommasTabulator {
+    fn tabulate(&self, config : &Config, names : Vec<Entry>) -> Output {
+        let mut rows : Output = vec![];
+        let mut line_width = 0;
+        for (i, entry) in names.iter().enumerate() {
+            let (mut item, mut width) = format_unpadded(config, entry);
+            if i + 1 < names.len() {
+                item.push(',');
+                width += 1;
+            }
+            match rows.last_mut() {
+                Some(row) if line_width + 1 + width <= config.max_width => {
+                    row.push(format!(" {}", item));
+                    line_width += 1 + width;
+                }
+                _ => {
+                    rows.push(vec![item]);
+                    line_width = width;
+                }
+            }
+        }
+        rows
+    }
+}
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Layout {
+    // Columns, filled top to bottom.
+    Grid,
+    // Columns, filled left to right.
+    Across,
+    OneLine,
+    Commas,
+    // EntryInfo for every entry, as JSON.
+    Json,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Layout, String> {
+        match value {
+            "grid" => Ok(Layout::Grid),
+            "across" => Ok(Layout::Across),
+            "oneline" => Ok(Layout::OneLine),
+            "commas" => Ok(Layout::Commas),
+            "json" => Ok(Layout::Json),
+            _ => Err(format!("Unknown Layout: {}", value)),
+        }
+    }
+}
+
+impl Layout {
+    pub fn tabulator(&self) -> Box<dyn Tabulator> {
+        match *self {
+            Layout::Grid | Layout::Across => Box::new(BinsearchTabulator),
+            Layout::OneLine => Box::new(OneLineTabulator),
+            Layout::Commas => Box::new(CommasTabulator),
+            Layout::Json => Box::new(JsonTabulator),
+        }
+    }
+
+    pub fn entry_order(&self) -> EntryOrder {
+        match *self {
+            Layout::Across => EntryOrder::Horizontal,
+            _ => EntryOrder::Vertical,
+        }
+    }
+}
+
+// How the grid layouts fit names into columns.
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GridAlgorithm {
+    Binsearch,
+    Planning,
+    Naive,
+}
+
+impl FromStr for GridAlgorithm {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<GridAlgorithm, String> {
+        match value {
+            "binsearch" => Ok(GridAlgorithm::Binsearch),
+            "planning" => Ok(GridAlgorithm::Planning),
+            "naive" => Ok(GridAlgorithm::Naive),
+            _ => Err(format!("Unknown GridAlgorithm: {}", value)),
+        }
+    }
+}
+
+impl GridAlgorithm {
+    pub fn tabulator(&self) -> Box<dyn Tabulator> {
+        match *self {
+            GridAlgorithm::Binsearch => Box::new(BinsearchTabulator),
+            GridAlgorithm::Planning => Box::new(PlanningTabulator),
+            GridAlgorithm::Naive => Box::new(NaiveTabulator),
+        }
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+    use std::path;
+    use self::super::super::formatter::ShortFormat;
+    use self::super::super::testing::default_config;
+
+    fn config(max_width : usize) -> Config {
+        Config {
+            entry: EntryConfig { icon_width: 0, ..default_config() },
+            max_width,
+            formatter: Box::new(ShortFormat),
+            entry_order: EntryOrder::Vertical,
+        }
+    }
+
+    // NOTE: Missing paths fall back to name-only attributes.
+    fn names(config : &Config) -> Vec<Entry> {
+        ["alpha.rs", "beta.rs", "gamma.rs", "delta.rs"].iter().map(|name| {
+            Entry::new(&config.entry, path::PathBuf::from(name), None)
+        }).collect()
+    }
+
+    fn plain(rows : Output) -> Vec<String> {
+        rows.iter().map(|row| {
+            let line : String = row.concat();
+            line.split('\x1b').enumerate().map(|(i, part)| if i == 0 { part } else { &part[part.find('m').unwrap() + 1..] }).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn for_one_per_line() {
+        let config = config(80);
+        assert_eq!(vec!["alpha.rs", "beta.rs", "gamma.rs", "delta.rs"], plain(OneLineTabulator.tabulate(&config, names(&config))));
+    }
+
+    #[test]
+    fn for_commas() {
+        let config = config(80);
+        assert_eq!(vec!["alpha.rs, beta.rs, gamma.rs, delta.rs"], plain(CommasTabulator.tabulate(&config, names(&config))));
+        let config = self::config(20);
+        assert_eq!(vec!["alpha.rs, beta.rs,", "gamma.rs, delta.rs"], plain(CommasTabulator.tabulate(&config, names(&config))));
+    }
+
+    #[test]
+    fn when_nothing_is_listed() {
+        let config = config(80);
+        for layout in &[Layout::Grid, Layout::Across, Layout::OneLine, Layout::Commas] {
+            assert_eq!(Vec::<Vec<String>>::new(), layout.tabulator().tabulate(&config, vec![]));
+        }
+        for tabulator in &[Box::new(PlanningTabulator) as Box<dyn Tabulator>, Box::new(NaiveTabulator)] {
+            assert_eq!(Vec::<Vec<String>>::new(), tabulator.tabulate(&config, vec![]));
+        }
+        assert_eq!(vec![vec!["[]".to_string()]], Layout::Json.tabulator().tabulate(&config, vec![]));
+    }
+
+    #[test]
+    fn when_parsing_layouts() {
+        assert_eq!(Ok(Layout::Commas), "commas".parse());
+        assert_eq!(EntryOrder::Horizontal, Layout::Across.entry_order());
+        assert!("tree".parse::<Layout>().is_err());
+        assert_eq!(Ok(GridAlgorithm::Planning), "planning".parse());
+        assert!("greedy".parse::<GridAlgorithm>().is_err());
+    }
+}
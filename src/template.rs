@@ -0,0 +1,342 @@
+use std::os::unix::fs::MetadataExt;
+use std::str::FromStr;
+
+use self::super::cell::{Cell, Segment};
+use self::super::colors::ColorType;
+use self::super::formatter::{decorated, strlen, Entry, EntryConfig, Formatter};
+use self::super::size::human_size;
+use self::super::timestamp::{TimeField, TimeFormat, TimeStyle};
+use self::super::users::UserDb;
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+enum Field {
+    Icon,
+    // File name, as in short format.
+    Name,
+    // Path as given, as in long format.
+    Path,
+    Indicator,
+    Key,
+    Kind,
+    Mime,
+    Size,
+    User,
+    Group,
+    Uid,
+    Gid,
+    Time(TimeField),
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Field, String> {
+        match value {
+            "icon" => Ok(Field::Icon),
+            "name" => Ok(Field::Name),
+            "path" => Ok(Field::Path),
+            "indicator" => Ok(Field::Indicator),
+            "key" => Ok(Field::Key),
+            "kind" => Ok(Field::Kind),
+            "mime" => Ok(Field::Mime),
+            "size" => Ok(Field::Size),
+            "user" => Ok(Field::User),
+            "group" => Ok(Field::Group),
+            "uid" => Ok(Field::Uid),
+            "gid" => Ok(Field::Gid),
+            "mtime" => Ok(Field::Time(TimeField::Modified)),
+            "atime" => Ok(Field::Time(TimeField::Accessed)),
+            "ctime" => Ok(Field::Time(TimeField::Changed)),
+            "btime" => Ok(Field::Time(TimeField::Created)),
+            _ => Err(format!("Unknown field: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Placeholder {
+    field: Field,
+    align: Option<(Align, usize)>,
+    color: bool,
+    // Sizes in KiB, MiB, ... instead of bytes.
+    human: bool,
+    // Times in another style than --time-style.
+    style: Option<TimeStyle>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Part {
+    Text(String),
+    Field(Placeholder),
+}
+
+// `{field:modifier:...}` placeholders between literal text, `{{` and `}}` for braces.
+// Modifiers are `<N`, `>N` or `^N` to align, `color`, `h` for sizes and a time
+// style for times. A `+FORMAT` time style takes the rest, colons included.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Template(Vec<Part>);
+
+fn parse_align(spec: &str) -> Option<(Align, usize)> {
+    let align = match spec.chars().next()? {
+        '<' => Align::Left,
+        '>' => Align::Right,
+        '^' => Align::Center,
+        _ => return None,
+    };
+    spec[1..].parse().ok().map(|width| (align, width))
+}
+
+fn parse_placeholder(spec: &str) -> Result<Placeholder, String> {
+    let mut modifiers = spec.splitn(2, ':');
+    let field: Field = modifiers.next().unwrap_or("").parse()?;
+    let mut placeholder = Placeholder { field, align: None, color: false, human: false, style: None };
+    let mut rest = modifiers.next();
+    while let Some(modifiers) = rest {
+        let mut split = modifiers.splitn(2, ':');
+        let modifier = split.next().unwrap_or("");
+        rest = split.next();
+        let is_time = matches!(field, Field::Time(_));
+        if let Some(align) = parse_align(modifier) {
+            placeholder.align = Some(align);
+        } else if modifier == "color" && !matches!(field, Field::Size | Field::Key | Field::Kind | Field::Mime) {
+            placeholder.color = true;
+        } else if modifier == "h" && field == Field::Size {
+            placeholder.human = true;
+        } else if is_time && modifier.starts_with('+') {
+            placeholder.style = Some(TimeStyle::Custom(modifiers[1..].to_string()));
+            rest = None;
+        } else if is_time && modifier.parse::<TimeStyle>().is_ok() {
+            placeholder.style = modifier.parse().ok();
+        } else {
+            return Err(format!("Unknown modifier for {}: {}", spec.split(':').next().unwrap_or(""), modifier));
+        }
+    }
+    Ok(placeholder)
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Template, String> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = value.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut spec = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => spec.push(c),
+                            None => return Err("Unmatched { in template".to_string()),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(text));
+                        text = String::new();
+                    }
+                    parts.push(Part::Field(parse_placeholder(&spec)?));
+                }
+                '}' => return Err("Unmatched } in template".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template(parts))
+    }
+}
+
+fn aligned(text: String, align: Option<(Align, usize)>) -> String {
+    let (align, width) = match align {
+        Some(align) => align,
+        None => return text,
+    };
+    let pad = width.saturating_sub(strlen(&text));
+    match align {
+        Align::Left => text + &" ".repeat(pad),
+        Align::Right => " ".repeat(pad) + &text,
+        Align::Center => " ".repeat(pad / 2) + &text + &" ".repeat(pad - pad / 2),
+    }
+}
+
+#[derive(Debug)]
+pub struct TemplateFormat {
+    pub template: Template,
+    pub time: TimeFormat,
+    pub users: UserDb,
+}
+
+impl TemplateFormat {
+    // Text of a field and the color it would get.
+    fn value(&self, config: &EntryConfig, entry: &Entry, placeholder: &Placeholder) -> (String, ColorType) {
+        let meta = entry.metadata();
+        let missing = || "-".to_string();
+        match placeholder.field {
+            Field::Icon if config.icon_width == 0 => (String::new(), entry.color()),
+            Field::Icon => (entry.icon().to_string(), entry.color()),
+            Field::Name => (decorated(config, entry, config.quoting.quote(entry.name())), entry.color()),
+            Field::Path => (decorated(config, entry, config.quoting.quote(entry.path.as_os_str())), entry.color()),
+            Field::Indicator => (entry.indicator().to_string(), entry.color()),
+            Field::Key => (entry.key().to_string(), ColorType::Normal),
+            Field::Kind => (entry.kind().name().to_string(), ColorType::Normal),
+            Field::Mime => (entry.mime().unwrap_or("-").to_string(), ColorType::Normal),
+            Field::Size => {
+                let size = meta.map(|meta| meta.len());
+                let text = match size {
+                    Some(size) if placeholder.human => human_size(size),
+                    Some(size) => size.to_string(),
+                    None => missing(),
+                };
+                (text, ColorType::Normal)
+            }
+            Field::User | Field::Uid => match meta {
+                Some(meta) => {
+                    let uid = meta.uid();
+                    let name = self.users.user_name(uid).filter(|_| placeholder.field == Field::User);
+                    (name.map(|n| n.to_string()).unwrap_or_else(|| uid.to_string()), self.users.user_color(uid))
+                }
+                None => (missing(), ColorType::Normal),
+            },
+            Field::Group | Field::Gid => match meta {
+                Some(meta) => {
+                    let gid = meta.gid();
+                    let name = self.users.group_name(gid).filter(|_| placeholder.field == Field::Group);
+                    (name.map(|n| n.to_string()).unwrap_or_else(|| gid.to_string()), self.users.group_color(gid))
+                }
+                None => (missing(), ColorType::Normal),
+            },
+            Field::Time(field) => {
+                let time = TimeFormat {
+                    style: placeholder.style.clone().unwrap_or_else(|| self.time.style.clone()),
+                    field,
+                    ..self.time.clone()
+                };
+                match meta.and_then(|meta| time.timestamp(meta)) {
+                    // NOTE: Styles pad for long format columns; here alignment is explicit.
+                    Some(t) => (time.format(t).trim_end().to_string(), time.color(t)),
+                    None => (missing(), ColorType::Normal),
+                }
+            }
+        }
+    }
+
+}
+
+impl Formatter for TemplateFormat {
+    fn cell(&self, config: &EntryConfig, entry: &Entry) -> Cell {
+        let mut cell = Cell::new();
+        for part in &self.template.0 {
+            let placeholder = match *part {
+                Part::Text(ref text) => {
+                    cell.push(Segment::new(text.clone(), None));
+                    continue;
+                }
+                Part::Field(ref placeholder) => placeholder,
+            };
+            let (text, color) = self.value(config, entry, placeholder);
+            let mut segment = Segment::new(aligned(text, placeholder.align), if placeholder.color { Some(color) } else { None });
+            match placeholder.field {
+                Field::Name | Field::Path => segment.link = Some(entry.path.clone()),
+                // Emoji icons take two columns but are a single grapheme.
+                Field::Icon if placeholder.align.is_none() && config.icon_width > 0 => segment.width = Some(config.icon_width),
+                _ => {}
+            }
+            cell.push(segment);
+        }
+        cell
+    }
+}
+
+#[cfg(test)]
+mod template_tests {
+    use super::*;
+    use termion::color;
+    use self::super::super::formatter::color_for;
+    use std::fs;
+    use std::path;
+    use std::time::{Duration, UNIX_EPOCH};
+    use self::super::super::testing::{default_config, TempDir};
+    use self::super::super::timestamp::Zone;
+
+    fn format(template: &str) -> TemplateFormat {
+        let missing = path::Path::new("tests/fixtures/missing");
+        TemplateFormat {
+            template: template.parse().unwrap(),
+            time: TimeFormat {
+                style: TimeStyle::Relative,
+                field: TimeField::Modified,
+                zone: Zone::Utc,
+                now: UNIX_EPOCH + Duration::from_secs(1_500_000_000 + 3 * 60 * 60),
+            },
+            users: UserDb::new(missing, missing, 0, 0),
+        }
+    }
+
+    fn entry(dir: &TempDir, config: &EntryConfig) -> Entry {
+        let path = dir.join("main.rs");
+        fs::write(&path, vec![b' '; 1536]).unwrap();
+        fs::File::options().write(true).open(&path).unwrap().set_modified(UNIX_EPOCH + Duration::from_secs(1_500_000_000)).unwrap();
+        Entry::new(config, path, None)
+    }
+
+    #[test]
+    fn for_plain_fields() {
+        let dir = TempDir::new("template-plain");
+        let config = default_config();
+        let entry = entry(&dir, &config);
+        let format = format("{name} {size} {size:h} {mtime} {mtime:+%Y-%m-%d %H:%M} {key}/{kind} {{x}}");
+        assert_eq!("main.rs 1536 1.5 KiB 3 hours ago 2017-07-14 02:40 rs/file {x}", format.cell(&config, &entry).text());
+        assert_eq!(format.predict(&config, &entry), strlen(&format.cell(&config, &entry).text()) + 1);
+    }
+
+    #[test]
+    fn for_alignment() {
+        let dir = TempDir::new("template-align");
+        let config = default_config();
+        let entry = entry(&dir, &config);
+        assert_eq!("[main.rs   |   1.5 KiB| rs ]", format("[{name:<10}|{size:h:>10}|{key:^4}]").cell(&config, &entry).text());
+    }
+
+    #[test]
+    fn for_colors() {
+        let dir = TempDir::new("template-color");
+        let config = EntryConfig { width: 30, ..default_config() };
+        let entry = entry(&dir, &config);
+        let format = format("{icon} {name:color}");
+        let expected = format!(
+            "{} {}main.rs{}",
+            config.files["rs"],
+            color::Fg(color_for(&config, &ColorType::RecognizedFile)),
+            color::Fg(color::Reset),
+        );
+        assert_eq!(expected.clone() + &" ".repeat(30 - 9), format.format(&config, &entry));
+        assert_eq!(10, format.predict(&config, &entry));
+    }
+
+    #[test]
+    fn when_parsing_fails() {
+        assert!("{nope}".parse::<Template>().is_err());
+        assert!("{size:color}".parse::<Template>().is_err());
+        assert!("{name:h}".parse::<Template>().is_err());
+        assert!("oops}".parse::<Template>().is_err());
+        assert!("{name".parse::<Template>().is_err());
+    }
+}
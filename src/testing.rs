@@ -0,0 +1,67 @@
+use std::env;
+use std::fs;
+use std::path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde_yaml;
+
+use self::super::formatter::EntryConfig;
+use self::super::quoting::Quoting;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Filesystem calls made for entries, as (call, path), across every test.
+static CALLS: Mutex<Vec<(&str, path::PathBuf)>> = Mutex::new(Vec::new());
+
+pub fn trace(call: &'static str, path: &path::Path) {
+    CALLS.lock().unwrap().push((call, path.to_path_buf()));
+}
+
+// Calls made so far for paths inside `dir`, like `["lstat", "read_dir"]`.
+pub fn calls_in(dir: &path::Path) -> Vec<&'static str> {
+    CALLS.lock().unwrap().iter().filter(|&(_, path)| path.starts_with(dir) && path != dir).map(|&(call, _)| call).collect()
+}
+
+// Scratch directory removed on drop, unique per test.
+pub struct TempDir(pub path::PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!("colorls-{}-{}-{}", name, process::id(), id));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn join(&self, name: &str) -> path::PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn default_config() -> EntryConfig {
+    EntryConfig {
+        files: serde_yaml::from_str(include_str!("default_config/files.yaml")).unwrap(),
+        folders: serde_yaml::from_str(include_str!("default_config/folders.yaml")).unwrap(),
+        file_aliases: serde_yaml::from_str(include_str!("default_config/file_aliases.yaml")).unwrap(),
+        folder_aliases: serde_yaml::from_str(include_str!("default_config/folder_aliases.yaml")).unwrap(),
+        colors: serde_yaml::from_str(include_str!("default_config/dark_colors.yaml")).unwrap(),
+        width: 40,
+        classify: false,
+        sniff: false,
+        mime: None,
+        mime_icons: serde_yaml::from_str(include_str!("default_config/mime.yaml")).unwrap(),
+        mime_colors: serde_yaml::from_str(include_str!("default_config/mime_colors.yaml")).unwrap(),
+        stat: true,
+        quoting: Quoting::default(),
+        hyperlink: false,
+        icon_width: 1,
+    }
+}
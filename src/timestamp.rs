@@ -0,0 +1,310 @@
+use std::ffi::CString;
+use std::fs;
+use std::mem;
+use std::os::unix::fs::MetadataExt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use libc;
+
+use self::super::colors::ColorType;
+
+// NOTE: libc does not export strftime, so declare it ourselves.
+extern "C" {
+    fn strftime(s: *mut libc::c_char, max: libc::size_t, format: *const libc::c_char, tm: *const libc::tm) -> libc::size_t;
+}
+
+// Longest +FORMAT output we try to fit, in bytes.
+const MAX_STRFTIME: usize = 64 * 1024;
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
+// Longest relative time is "59 seconds ago".
+const RELATIVE_WIDTH: usize = 14;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TimeStyle {
+    Relative,
+    Iso,
+    LongIso,
+    FullIso,
+    Custom(String),
+}
+
+impl FromStr for TimeStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<TimeStyle, String> {
+        match value {
+            "relative" => Ok(TimeStyle::Relative),
+            "iso" => Ok(TimeStyle::Iso),
+            "long-iso" => Ok(TimeStyle::LongIso),
+            "full-iso" => Ok(TimeStyle::FullIso),
+            _ if value.starts_with('+') => Ok(TimeStyle::Custom(value[1..].to_string())),
+            _ => Err(format!("Unknown TimeStyle: {}", value)),
+        }
+    }
+}
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TimeField {
+    Modified,
+    Accessed,
+    Changed,
+    Created,
+}
+
+impl FromStr for TimeField {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<TimeField, String> {
+        match value {
+            "modified" | "mtime" => Ok(TimeField::Modified),
+            "accessed" | "atime" | "access" | "use" => Ok(TimeField::Accessed),
+            "changed" | "ctime" | "status" => Ok(TimeField::Changed),
+            "created" | "birth" | "creation" => Ok(TimeField::Created),
+            _ => Err(format!("Unknown TimeField: {}", value)),
+        }
+    }
+}
+
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Zone {
+    Local,
+    Utc,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeFormat {
+    pub style: TimeStyle,
+    pub field: TimeField,
+    pub zone: Zone,
+    // Relative times and colors are computed against this, so tests can pin it.
+    pub now: SystemTime,
+}
+
+impl TimeFormat {
+    pub fn new(style: TimeStyle, field: TimeField) -> TimeFormat {
+        TimeFormat {
+            style,
+            field,
+            zone: Zone::Local,
+            now: SystemTime::now(),
+        }
+    }
+
+    pub fn timestamp(&self, meta: &fs::Metadata) -> Option<SystemTime> {
+        match self.field {
+            TimeField::Modified => meta.modified().ok(),
+            TimeField::Accessed => meta.accessed().ok(),
+            TimeField::Created => meta.created().ok(),
+            TimeField::Changed => Some(from_unix(meta.ctime(), meta.ctime_nsec() as u32)),
+        }
+    }
+
+    pub fn format(&self, time: SystemTime) -> String {
+        match self.style {
+            TimeStyle::Relative => format!("{:<width$}", relative(self.now, time), width = RELATIVE_WIDTH),
+            TimeStyle::Iso => {
+                if age(self.now, time) < 6 * MONTH {
+                    self.strftime("%m-%d %H:%M", time)
+                } else {
+                    self.strftime("%Y-%m-%d ", time)
+                }
+            }
+            TimeStyle::LongIso => self.strftime("%Y-%m-%d %H:%M", time),
+            TimeStyle::FullIso => self.strftime("%Y-%m-%d %H:%M:%S.%N %z", time),
+            TimeStyle::Custom(ref fmt) => self.strftime(fmt, time),
+        }
+    }
+
+    pub fn color(&self, time: SystemTime) -> ColorType {
+        let age = age(self.now, time);
+        if age < HOUR {
+            ColorType::HourOld
+        } else if age < DAY {
+            ColorType::DayOld
+        } else {
+            ColorType::NoModifier
+        }
+    }
+
+    fn strftime(&self, fmt: &str, time: SystemTime) -> String {
+        let (secs, nanos) = to_unix(time);
+        let fmt = match CString::new(expand_nanos(fmt, nanos)) {
+            Ok(fmt) => fmt,
+            Err(_) => return String::new(),
+        };
+        let secs = secs as libc::time_t;
+        let tm = unsafe {
+            let mut tm: libc::tm = mem::zeroed();
+            match self.zone {
+                Zone::Local => libc::localtime_r(&secs, &mut tm),
+                Zone::Utc => libc::gmtime_r(&secs, &mut tm),
+            };
+            tm
+        };
+        // NOTE: strftime returns 0 both when the buffer is too small and when
+        // the output is empty, so grow it up to a cap before giving up.
+        let mut buf = vec![0u8; 256];
+        while buf.len() <= MAX_STRFTIME {
+            let len = unsafe { strftime(buf.as_mut_ptr() as *mut libc::c_char, buf.len(), fmt.as_ptr(), &tm) };
+            if len > 0 {
+                return String::from_utf8_lossy(&buf[..len]).into_owned();
+            }
+            let size = buf.len() * 2;
+            buf.resize(size, 0);
+        }
+        String::new()
+    }
+}
+
+// NOTE: strftime has no %N, so fill in nanoseconds (like GNU date) up front.
+fn expand_nanos(fmt: &str, nanos: u32) -> String {
+    let mut out = String::with_capacity(fmt.len());
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('N') => out.push_str(&format!("{:09}", nanos)),
+            Some(next) => {
+                out.push(c);
+                out.push(next);
+            }
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+fn from_unix(secs: i64, nanos: u32) -> SystemTime {
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::new(secs as u64, nanos)
+    } else {
+        UNIX_EPOCH - Duration::new((-secs) as u64, 0) + Duration::new(0, nanos)
+    }
+}
+
+fn to_unix(time: SystemTime) -> (i64, u32) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            if d.subsec_nanos() == 0 {
+                (-(d.as_secs() as i64), 0)
+            } else {
+                (-(d.as_secs() as i64) - 1, 1_000_000_000 - d.subsec_nanos())
+            }
+        }
+    }
+}
+
+// Files from the future count as brand new.
+fn age(now: SystemTime, time: SystemTime) -> u64 {
+    now.duration_since(time).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn plural(n: u64, unit: &str) -> String {
+    if n == 1 {
+        format!("{} {} ago", n, unit)
+    } else {
+        format!("{} {}s ago", n, unit)
+    }
+}
+
+fn relative(now: SystemTime, time: SystemTime) -> String {
+    if time > now {
+        return "in the future".to_string();
+    }
+    match age(now, time) {
+        0 => "just now".to_string(),
+        n if n < MINUTE => plural(n, "second"),
+        n if n < HOUR => plural(n / MINUTE, "minute"),
+        n if n < DAY => plural(n / HOUR, "hour"),
+        n if n < MONTH => plural(n / DAY, "day"),
+        n if n < YEAR => plural(n / MONTH, "month"),
+        n => plural(n / YEAR, "year"),
+    }
+}
+
+#[cfg(test)]
+mod relative_tests {
+    use super::*;
+
+    fn ago(secs: u64) -> String {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        relative(now, now - Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn for_simple_cases() {
+        assert_eq!("just now", ago(0));
+        assert_eq!("1 second ago", ago(1));
+        assert_eq!("59 seconds ago", ago(59));
+        assert_eq!("3 hours ago", ago(3 * HOUR + 5));
+        assert_eq!("2 days ago", ago(2 * DAY));
+        assert_eq!("1 year ago", ago(YEAR + DAY));
+    }
+
+    #[test]
+    fn when_time_is_in_the_future() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        assert_eq!("in the future", relative(now, now + Duration::from_secs(1)))
+    }
+}
+
+#[cfg(test)]
+mod time_format_tests {
+    use super::*;
+
+    fn format_with(style: TimeStyle, age: u64) -> (String, ColorType) {
+        let time = UNIX_EPOCH + Duration::new(1_500_000_000, 123_456_789);
+        let format = TimeFormat {
+            style,
+            field: TimeField::Modified,
+            zone: Zone::Utc,
+            now: time + Duration::from_secs(age),
+        };
+        (format.format(time), format.color(time))
+    }
+
+    #[test]
+    fn for_relative_style() {
+        assert_eq!(("3 hours ago   ".to_string(), ColorType::DayOld), format_with(TimeStyle::Relative, 3 * HOUR))
+    }
+
+    #[test]
+    fn for_iso_styles() {
+        assert_eq!("07-14 02:40", format_with(TimeStyle::Iso, MINUTE).0);
+        assert_eq!("2017-07-14 ", format_with(TimeStyle::Iso, YEAR).0);
+        assert_eq!("2017-07-14 02:40", format_with(TimeStyle::LongIso, MINUTE).0);
+        assert_eq!("2017-07-14 02:40:00.123456789 +0000", format_with(TimeStyle::FullIso, MINUTE).0);
+    }
+
+    #[test]
+    fn for_custom_style() {
+        assert_eq!("2017/07/14 %N 123456789", format_with("+%Y/%m/%d %%N %N".parse().unwrap(), MINUTE).0);
+        let long = format!("+{}", "%Y-%m-%d ".repeat(100));
+        assert_eq!("2017-07-14 ".repeat(100), format_with(long.parse().unwrap(), MINUTE).0);
+    }
+
+    #[test]
+    fn for_age_colors() {
+        assert_eq!(ColorType::HourOld, format_with(TimeStyle::LongIso, MINUTE).1);
+        assert_eq!(ColorType::NoModifier, format_with(TimeStyle::LongIso, 2 * DAY).1);
+    }
+
+    #[test]
+    fn when_parsing_fields() {
+        assert_eq!(Ok(TimeField::Changed), "changed".parse());
+        assert_eq!(Ok(TimeField::Created), "birth".parse());
+        assert!("yesterday".parse::<TimeField>().is_err());
+    }
+}
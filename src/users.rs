@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path;
+use std::sync::OnceLock;
+
+use libc;
+
+use self::super::colors::ColorType;
+
+pub const PASSWD: &str = "/etc/passwd";
+pub const GROUP: &str = "/etc/group";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Group {
+    name: String,
+    members: Vec<String>,
+}
+
+// Names for uids and gids, read from passwd/group files on first use.
+#[derive(Debug)]
+pub struct UserDb {
+    passwd: path::PathBuf,
+    group: path::PathBuf,
+    // The invoking user, whose entries get highlighted.
+    pub uid: u32,
+    pub gid: u32,
+    users: OnceLock<HashMap<u32, String>>,
+    groups: OnceLock<HashMap<u32, Group>>,
+}
+
+// `name:password:id:...` lines, as used by both passwd and group.
+fn parse_db<T, F>(path: &path::Path, f: F) -> HashMap<u32, T>
+    where F: Fn(&[&str]) -> T {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    let mut db = HashMap::new();
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        if line.starts_with('#') || fields.len() < 3 {
+            continue;
+        }
+        if let Ok(id) = fields[2].parse() {
+            db.entry(id).or_insert_with(|| f(&fields));
+        }
+    }
+    db
+}
+
+impl UserDb {
+    pub fn new(passwd: &path::Path, group: &path::Path, uid: u32, gid: u32) -> UserDb {
+        UserDb {
+            passwd: passwd.to_path_buf(),
+            group: group.to_path_buf(),
+            uid,
+            gid,
+            users: OnceLock::new(),
+            groups: OnceLock::new(),
+        }
+    }
+
+    pub fn system() -> UserDb {
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        UserDb::new(path::Path::new(PASSWD), path::Path::new(GROUP), uid, gid)
+    }
+
+    fn users(&self) -> &HashMap<u32, String> {
+        self.users.get_or_init(|| parse_db(&self.passwd, |fields| fields[0].to_string()))
+    }
+
+    fn groups(&self) -> &HashMap<u32, Group> {
+        self.groups.get_or_init(|| {
+            parse_db(&self.group, |fields| Group {
+                name: fields[0].to_string(),
+                members: fields.get(3).map(|m| m.split(',').filter(|m| !m.is_empty()).map(|m| m.to_string()).collect()).unwrap_or_default(),
+            })
+        })
+    }
+
+    pub fn user_name(&self, uid: u32) -> Option<&str> {
+        self.users().get(&uid).map(|name| &name[..])
+    }
+
+    pub fn group_name(&self, gid: u32) -> Option<&str> {
+        self.groups().get(&gid).map(|group| &group.name[..])
+    }
+
+    pub fn user_color(&self, uid: u32) -> ColorType {
+        if uid == self.uid {
+            ColorType::User
+        } else if uid == 0 {
+            ColorType::Root
+        } else {
+            ColorType::OtherUser
+        }
+    }
+
+    pub fn group_color(&self, gid: u32) -> ColorType {
+        let is_member = match (self.groups().get(&gid), self.user_name(self.uid)) {
+            (Some(group), Some(name)) => group.members.iter().any(|m| m == name),
+            _ => false,
+        };
+        if gid == self.gid || is_member {
+            ColorType::User
+        } else if gid == 0 {
+            ColorType::Root
+        } else {
+            ColorType::OtherUser
+        }
+    }
+}
+
+#[cfg(test)]
+mod user_db_tests {
+    use super::*;
+
+    fn db() -> UserDb {
+        UserDb::new(path::Path::new("tests/fixtures/passwd"), path::Path::new("tests/fixtures/group"), 1000, 1000)
+    }
+
+    #[test]
+    fn for_names() {
+        assert_eq!(Some("root"), db().user_name(0));
+        assert_eq!(Some("alice"), db().user_name(1000));
+        assert_eq!(Some("wheel"), db().group_name(10));
+        assert_eq!(None, db().user_name(4242));
+    }
+
+    #[test]
+    fn for_user_colors() {
+        assert_eq!(ColorType::User, db().user_color(1000));
+        assert_eq!(ColorType::Root, db().user_color(0));
+        assert_eq!(ColorType::OtherUser, db().user_color(1001));
+    }
+
+    #[test]
+    fn for_group_colors() {
+        assert_eq!(ColorType::User, db().group_color(1000));
+        assert_eq!(ColorType::User, db().group_color(10));
+        assert_eq!(ColorType::Root, db().group_color(0));
+        assert_eq!(ColorType::OtherUser, db().group_color(1001));
+    }
+
+    #[test]
+    fn when_files_are_missing() {
+        let db = UserDb::new(path::Path::new("tests/fixtures/missing"), path::Path::new("tests/fixtures/missing"), 0, 0);
+        assert_eq!(None, db.user_name(0));
+        assert_eq!(ColorType::User, db.group_color(0));
+    }
+}